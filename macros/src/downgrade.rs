@@ -0,0 +1,80 @@
+use proc_macro::{Delimiter, TokenStream};
+
+use crate::parse::{mentions, Error, Fields, GenericParam, Item, Result};
+use crate::tokens::{code, Tokens};
+
+pub(crate) fn derive(input: TokenStream) -> Result<TokenStream> {
+    let item = Item::parse(input)?;
+    let Some(mutability) = item.generics.params.iter().find_map(|param| match param {
+        GenericParam::Type { name, bounds } if mentions(bounds.clone(), "Mutability") => {
+            Some(name.to_string())
+        }
+        _ => None,
+    }) else {
+        return Err(Error::new(
+            item.name.span(),
+            "`Downgrade` can only be derived for structs with a type parameter bounded by `Mutability`",
+        ));
+    };
+    let downgraded_type =
+        item.self_type_replacing(&mutability, code("::generic_mutability::Shared"));
+
+    // Fields whose type mentions the mutability parameter are downgraded, the others are moved.
+    let field_value = |ty: &TokenStream, member: TokenStream| {
+        let mut tokens = Tokens::new();
+        if mentions(ty.clone(), &mutability) {
+            tokens
+                .code("::generic_mutability::Downgrade::downgrade")
+                .group(Delimiter::Parenthesis, |tokens| {
+                    tokens.code("self.").tokens(member);
+                });
+        } else {
+            tokens.code("self.").tokens(member);
+        }
+        TokenStream::from(tokens)
+    };
+    let mut body = Tokens::new();
+    body.ident(&item.name);
+    match &item.fields {
+        Fields::Named(fields) => {
+            body.group(Delimiter::Brace, |tokens| {
+                for field in fields {
+                    tokens
+                        .tokens(field.member())
+                        .code(":")
+                        .tokens(field_value(&field.ty, field.member()))
+                        .code(",");
+                }
+            });
+        }
+        Fields::Unnamed(fields) => {
+            body.group(Delimiter::Parenthesis, |tokens| {
+                for field in fields {
+                    tokens
+                        .tokens(field_value(&field.ty, field.member()))
+                        .code(",");
+                }
+            });
+        }
+        Fields::Unit => {}
+    }
+
+    let mut output = Tokens::new();
+    output
+        .code("#[automatically_derived] impl")
+        .tokens(item.generics.impl_generics(&[]))
+        .code("::generic_mutability::Downgrade for")
+        .tokens(item.self_type())
+        .tokens(item.generics.where_clause(&[]))
+        .group(Delimiter::Brace, |tokens| {
+            tokens
+                .code("type Downgraded =")
+                .tokens(downgraded_type)
+                .code(";")
+                .code("#[inline] fn downgrade(self) -> Self::Downgraded")
+                .group(Delimiter::Brace, |tokens| {
+                    tokens.tokens(body);
+                });
+        });
+    Ok(output.into())
+}
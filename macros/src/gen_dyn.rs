@@ -0,0 +1,600 @@
+use proc_macro::{Delimiter, Ident, Span, TokenStream, TokenTree};
+
+use crate::parse::{
+    generic_arguments, genref_parts, mentions, replace_ident, Cursor, Error, Function,
+    GenericParam, Generics, Result,
+};
+use crate::tokens::{code, Tokens};
+
+/// The return type of a generic method, `GenRef<'s, M, T>` or `Option<GenRef<'s, M, T>>`.
+struct GenOutput {
+    lifetime: TokenStream,
+    target: TokenStream,
+    optional: bool,
+}
+
+impl GenOutput {
+    fn parse(output: &TokenStream, m: &str) -> Option<GenOutput> {
+        if let Some((lifetime, target)) = genref_parts(output, m) {
+            return Some(GenOutput {
+                lifetime,
+                target,
+                optional: false,
+            });
+        }
+        let [argument] = <[TokenStream; 1]>::try_from(generic_arguments(output, "Option")?).ok()?;
+        let (lifetime, target) = genref_parts(&argument, m)?;
+        Some(GenOutput {
+            lifetime,
+            target,
+            optional: true,
+        })
+    }
+
+    /// Wraps `ty` in an `Option` if the output is optional.
+    fn wrap(&self, ty: TokenStream) -> TokenStream {
+        let mut tokens = Tokens::new();
+        if self.optional {
+            tokens.code("::core::option::Option<").tokens(ty).code(">");
+        } else {
+            tokens.tokens(ty);
+        }
+        tokens.into()
+    }
+
+    /// Emits `convert(value, extra)`, through `Option::map` if the output is optional.
+    fn convert(&self, value: TokenStream, convert: &str, extra: &str) -> TokenStream {
+        let mut tokens = Tokens::new();
+        if self.optional {
+            tokens
+                .code("::core::option::Option::map")
+                .group(Delimiter::Parenthesis, |tokens| {
+                    tokens
+                        .tokens(value)
+                        .code(&format!(", |value| {convert}(value {extra})"));
+                });
+        } else {
+            tokens
+                .code(convert)
+                .group(Delimiter::Parenthesis, |tokens| {
+                    tokens.tokens(value).code(extra);
+                });
+        }
+        tokens.into()
+    }
+}
+
+enum Kind {
+    /// A method generic over the mutability `m`, taking a `GenRef<'lifetime, M, Self>`.
+    Generic {
+        m: String,
+        lifetime: TokenStream,
+        output: GenOutput,
+    },
+    /// A dyn-compatible method, which is forwarded as it is.
+    Forwarded,
+}
+
+/// A required method of the trait.
+struct Method {
+    function: Function,
+    /// The tokens of the declaration, without the `;`.
+    declaration: TokenStream,
+    /// The names of the parameters after the receiver.
+    arguments: Vec<Ident>,
+    kind: Kind,
+}
+
+impl Method {
+    fn parse(function: Function, mut declaration: Vec<TokenTree>) -> Result<Method> {
+        let span = function.name.span();
+        declaration.pop();
+        let Some((first, rest)) = function.inputs.split_first() else {
+            return Err(Error::new(
+                span,
+                "associated functions without a receiver are not dyn-compatible",
+            ));
+        };
+        let arguments = rest
+            .iter()
+            .map(|arg| {
+                arg.binding().ok_or_else(|| {
+                    Error::new(
+                        span,
+                        "the parameters of required methods must be named to be forwarded",
+                    )
+                })
+            })
+            .collect::<Result<Vec<Ident>>>()?;
+        let m = function
+            .generics
+            .params
+            .iter()
+            .find_map(|param| match param {
+                GenericParam::Type { name, bounds } if mentions(bounds.clone(), "Mutability") => {
+                    Some(name.to_string())
+                }
+                _ => None,
+            });
+        let type_params = function
+            .generics
+            .params
+            .iter()
+            .filter(|param| !param.is_lifetime())
+            .count();
+        let kind = match m {
+            Some(m) => {
+                if type_params > 1 {
+                    return Err(Error::new(
+                        span,
+                        "generic parameters other than the mutability and lifetimes are not dyn-compatible",
+                    ));
+                }
+                let Some((lifetime, _)) = first
+                    .ty
+                    .as_ref()
+                    .and_then(|ty| genref_parts(ty, &m))
+                    .filter(|(_, target)| target.to_string() == "Self")
+                else {
+                    return Err(Error::new(
+                        span,
+                        format!("expected `GenRef<'_, {m}, Self>` as the first parameter"),
+                    ));
+                };
+                let Some(output) = function
+                    .output
+                    .as_ref()
+                    .and_then(|output| GenOutput::parse(output, &m))
+                else {
+                    return Err(Error::new(
+                        span,
+                        format!("expected `GenRef<'_, {m}, T>` or `Option<GenRef<'_, {m}, T>>` as the return type"),
+                    ));
+                };
+                let others = rest.iter().filter_map(|arg| arg.ty.clone()).chain([
+                    output.target.clone(),
+                    function.generics.where_predicates.clone(),
+                ]);
+                for tokens in others {
+                    if mentions(tokens, &m) {
+                        return Err(Error::new(
+                            span,
+                            format!("`{m}` can only be used as the mutability of the `GenRef`s"),
+                        ));
+                    }
+                }
+                Kind::Generic {
+                    m,
+                    lifetime,
+                    output,
+                }
+            }
+            None => {
+                if type_params > 0 {
+                    return Err(Error::new(
+                        span,
+                        "methods with type or const parameters are not dyn-compatible",
+                    ));
+                }
+                if first.ty.is_some() || !mentions(first.pattern.clone(), "self") {
+                    return Err(Error::new(
+                        span,
+                        "required methods must take `&self` or `&mut self`, or be generic over the mutability",
+                    ));
+                }
+                Kind::Forwarded
+            }
+        };
+        Ok(Method {
+            function,
+            declaration: declaration.into_iter().collect(),
+            arguments,
+            kind,
+        })
+    }
+
+    /// Emits `path(first, arguments...)`.
+    fn call(&self, tokens: &mut Tokens, path: TokenStream, first: &str) {
+        tokens.tokens(path).group(Delimiter::Parenthesis, |tokens| {
+            tokens.code(first);
+            for name in &self.arguments {
+                tokens.code(",").ident(name);
+            }
+        });
+    }
+
+    /// Emits the parameters after the receiver, with `map` applied to their types.
+    fn rest_inputs(&self, tokens: &mut Tokens, map: impl Fn(TokenStream) -> TokenStream) {
+        for (arg, name) in self.function.inputs[1..].iter().zip(&self.arguments) {
+            tokens
+                .ident(name)
+                .code(":")
+                .tokens(map(arg.ty.clone().unwrap_or_default()))
+                .code(",");
+        }
+    }
+
+    /// The lifetime parameters of the method, except `except`.
+    fn lifetimes(&self, except: Option<&TokenStream>) -> Generics {
+        Generics {
+            params: self
+                .function
+                .generics
+                .params
+                .iter()
+                .filter(|param| param.is_lifetime())
+                .filter(|param| {
+                    except.is_none_or(|except| param.argument().to_string() != except.to_string())
+                })
+                .cloned()
+                .collect(),
+            where_predicates: self.function.generics.where_predicates.clone(),
+        }
+    }
+}
+
+/// The entries of the companion trait for a generic method: `(suffix, reference, into, from, gen_into, gen_from, variant)`.
+const ENTRIES: [(&str, &str, &str, &str, &str, &str, &str); 2] = [
+    (
+        "shared",
+        "",
+        "into_shared",
+        "from_shared",
+        "gen_into_shared",
+        "gen_from_shared",
+        "Shared",
+    ),
+    (
+        "mut",
+        "mut",
+        "into_mut",
+        "from_mut",
+        "gen_into_mut",
+        "gen_from_mut",
+        "Mutable",
+    ),
+];
+
+pub(crate) fn expand(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+    if let Some(token) = args.into_iter().next() {
+        return Err(Error::new(
+            token.span(),
+            "`gen_dyn` does not take arguments",
+        ));
+    }
+    let mut header: Vec<TokenTree> = input.clone().into_iter().collect();
+    let body = match header.pop() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "this macro can only be applied to traits",
+            ))
+        }
+    };
+    let mut cursor = Cursor::new(header.into_iter().collect());
+    cursor.attributes();
+    let vis = cursor.visibility();
+    if !cursor.keyword("trait") {
+        return Err(Error::new(
+            Span::call_site(),
+            "this macro can only be applied to safe traits",
+        ));
+    }
+    let name = cursor
+        .ident()
+        .ok_or_else(|| cursor.error("expected an identifier"))?;
+    if cursor.is_punct('<') {
+        return Err(cursor.error("generic traits are not supported by `gen_dyn`"));
+    }
+    let supertraits = cursor.punct(':').then(|| {
+        let mut tokens = Vec::new();
+        while !cursor.is_empty() && !cursor.is_ident("where") {
+            tokens.extend(cursor.next());
+        }
+        tokens.into_iter().collect::<TokenStream>()
+    });
+    if !cursor.is_empty() {
+        return Err(cursor.error("`where` clauses on the trait are not supported by `gen_dyn`"));
+    }
+
+    let mut methods = Vec::new();
+    let mut items = Cursor::new(body.stream());
+    while !items.is_empty() {
+        if !items.is_function() {
+            return Err(
+                items.error("associated types and constants are not supported by `gen_dyn`")
+            );
+        }
+        let start = items.position();
+        let function = Function::parse_from(&mut items)?;
+        // Provided methods are available on `dyn` through the implementation of the trait.
+        if function.body.is_none() {
+            let declaration = items.consumed_since(start).into_iter().collect();
+            methods.push(Method::parse(function, declaration)?);
+        }
+    }
+
+    let companion = Ident::new(&format!("{name}Dyn"), name.span());
+    let extension = Ident::new(&format!("{name}GenDyn"), name.span());
+    let all_generics = Generics {
+        params: methods
+            .iter()
+            .flat_map(|method| method.function.generics.params.iter().cloned())
+            .collect(),
+        where_predicates: TokenStream::new(),
+    };
+    let a = code(&all_generics.fresh_lifetime_name("a"));
+    let ext_m = all_generics.fresh_type_name("M");
+    // The lifetime of the trait object, which may outlive the borrow.
+    let object = code(&all_generics.fresh_lifetime_name("object"));
+    let trait_path = |implementor: &str, item: &Ident| {
+        let mut tokens = Tokens::new();
+        tokens
+            .code(&format!("<{implementor} as"))
+            .ident(&name)
+            .code(">::")
+            .ident(item);
+        TokenStream::from(tokens)
+    };
+    let companion_path = |item: &Ident| {
+        let mut tokens = Tokens::new();
+        tokens.ident(&companion).code("::").ident(item);
+        TokenStream::from(tokens)
+    };
+
+    let mut entries = Tokens::new();
+    let mut blanket = Tokens::new();
+    let mut dyn_impl = Tokens::new();
+    let mut ext_methods = Tokens::new();
+    let mut ext_impl = Tokens::new();
+    for method in &methods {
+        let function_name = &method.function.name;
+        let Kind::Generic {
+            m,
+            lifetime,
+            output,
+        } = &method.kind
+        else {
+            entries.tokens(method.declaration.clone()).code(";");
+            blanket
+                .code("#[inline]")
+                .tokens(method.declaration.clone())
+                .group(Delimiter::Brace, |tokens| {
+                    method.call(tokens, trait_path("__Implementor", function_name), "self")
+                });
+            dyn_impl
+                .code("#[inline]")
+                .tokens(method.declaration.clone())
+                .group(Delimiter::Brace, |tokens| {
+                    method.call(tokens, companion_path(function_name), "self")
+                });
+            continue;
+        };
+
+        let mut arms = Tokens::new();
+        for (suffix, reference, into, from, gen_into, gen_from, variant) in ENTRIES {
+            let entry = Ident::new(&format!("{function_name}_{suffix}"), function_name.span());
+            let lifetimes = method.lifetimes(None);
+            let mut signature = Tokens::new();
+            signature
+                .code("fn")
+                .ident(&entry)
+                .tokens(lifetimes.impl_generics(&[]))
+                .group(Delimiter::Parenthesis, |tokens| {
+                    tokens.code("&");
+                    if lifetime.to_string() != "'_" {
+                        tokens.tokens(lifetime.clone());
+                    }
+                    tokens.code(reference).code("self,");
+                    method.rest_inputs(tokens, |ty| ty);
+                })
+                .code("->");
+            let mut returned = Tokens::new();
+            returned
+                .code("&")
+                .tokens(output.lifetime.clone())
+                .code(reference)
+                .tokens(output.target.clone());
+            signature
+                .tokens(output.wrap(returned.into()))
+                .tokens(lifetimes.where_clause(&[]));
+            let signature = TokenStream::from(signature);
+
+            entries
+                .doc(&format!(
+                    " The {} entry of [`{name}::{function_name}`].",
+                    if reference.is_empty() {
+                        "shared"
+                    } else {
+                        "mutable"
+                    },
+                ))
+                .tokens(signature.clone())
+                .code(";");
+
+            let mut call = Tokens::new();
+            method.call(
+                &mut call,
+                trait_path("__Implementor", function_name),
+                &format!("::generic_mutability::GenRef::{from}(self)"),
+            );
+            blanket
+                .code("#[inline]")
+                .tokens(signature)
+                .group(Delimiter::Brace, |tokens| {
+                    tokens.tokens(output.convert(
+                        call.into(),
+                        &format!("::generic_mutability::GenRef::{into}"),
+                        "",
+                    ));
+                });
+
+            let mut call = Tokens::new();
+            method.call(
+                &mut call,
+                companion_path(&entry),
+                &format!("::generic_mutability::GenRef::{gen_into}(this, __proof)"),
+            );
+            arms.code(&format!(
+                "::generic_mutability::MutabilityEnum::{variant}(__proof) =>"
+            ))
+            .tokens(output.convert(
+                call.into(),
+                &format!("::generic_mutability::GenRef::{gen_from}"),
+                ", __proof",
+            ))
+            .code(",");
+        }
+        dyn_impl
+            .code("#[inline]")
+            .tokens(method.declaration.clone())
+            .group(Delimiter::Brace, |tokens| {
+                tokens
+                    .code(&format!(
+                        "match <{m} as ::generic_mutability::Mutability>::mutability()"
+                    ))
+                    .group(Delimiter::Brace, |tokens| {
+                        tokens.tokens(arms);
+                    });
+            });
+
+        // In the extension trait, the `GenRef` lifetime and the mutability become the ones of the trait.
+        let replace = |tokens: TokenStream| {
+            let tokens = replace_ident(tokens, m, &code(&ext_m));
+            match lifetime.to_string().as_str() {
+                "'_" => tokens,
+                name => replace_ident(tokens, name, &a),
+            }
+        };
+        let lifetimes = method.lifetimes(Some(lifetime));
+        let lifetimes = Generics {
+            params: lifetimes.params,
+            where_predicates: replace(lifetimes.where_predicates),
+        };
+        let mut returned = Tokens::new();
+        returned
+            .code("::generic_mutability::GenRef<")
+            .tokens(if output.lifetime.to_string() == "'_" {
+                a.clone()
+            } else {
+                replace(output.lifetime.clone())
+            })
+            .code(&format!(", {ext_m},"))
+            .tokens(replace(output.target.clone()))
+            .code(">");
+        let mut signature = Tokens::new();
+        signature
+            .code("fn")
+            .ident(function_name)
+            .tokens(lifetimes.impl_generics(&[]))
+            .group(Delimiter::Parenthesis, |tokens| {
+                tokens.code("self,");
+                method.rest_inputs(tokens, replace);
+            })
+            .code("->")
+            .tokens(output.wrap(returned.into()))
+            .tokens(lifetimes.where_clause(&[]));
+        let signature = TokenStream::from(signature);
+        ext_methods
+            .doc(&format!(" Calls [`{name}::{function_name}`]."))
+            .tokens(signature.clone())
+            .code(";");
+        let mut path = Tokens::new();
+        path.code("<dyn")
+            .ident(&companion)
+            .code("+")
+            .tokens(object.clone())
+            .code("as")
+            .ident(&name)
+            .code(">::")
+            .ident(function_name);
+        ext_impl
+            .code("#[inline]")
+            .tokens(signature)
+            .group(Delimiter::Brace, |tokens| {
+                method.call(
+                    tokens,
+                    path.into(),
+                    "::generic_mutability::GenDyn::into_genref(self)",
+                );
+            });
+    }
+
+    let mut output = Tokens::new();
+    output.tokens(input);
+
+    output
+        .doc(&format!(
+            " The dyn-compatible companion of [`{name}`], with a shared and a mutable entry for every method that is generic over the mutability."
+        ))
+        .doc("")
+        .doc(&format!(
+            " It is implemented for every type implementing [`{name}`], and `dyn {companion}` implements [`{name}`] in turn."
+        ))
+        .tokens(vis.clone())
+        .code("trait")
+        .ident(&companion);
+    if let Some(supertraits) = supertraits {
+        output.code(":").tokens(supertraits);
+    }
+    output.group(Delimiter::Brace, |tokens| {
+        tokens.tokens(entries);
+    });
+
+    output
+        .code("impl<__Implementor:")
+        .ident(&name)
+        .code(">")
+        .ident(&companion)
+        .code("for __Implementor")
+        .group(Delimiter::Brace, |tokens| {
+            tokens.tokens(blanket);
+        });
+
+    output
+        .code("impl")
+        .ident(&name)
+        .code("for dyn")
+        .ident(&companion)
+        .code("+ '_")
+        .group(Delimiter::Brace, |tokens| {
+            tokens.tokens(dyn_impl);
+        });
+
+    output
+        .doc(&format!(
+            " The generically mutable methods of [`{name}`] as methods of [`GenDyn`](::generic_mutability::GenDyn)`<'_, M, dyn {companion}>`."
+        ))
+        .tokens(vis)
+        .code("trait")
+        .ident(&extension)
+        .code("<")
+        .tokens(a.clone())
+        .code(&format!(", {ext_m}: ::generic_mutability::Mutability>"))
+        .group(Delimiter::Brace, |tokens| {
+            tokens.tokens(ext_methods);
+        });
+
+    output
+        .code("impl<")
+        .tokens(a.clone())
+        .code(",")
+        .tokens(object.clone())
+        .code(&format!(", {ext_m}: ::generic_mutability::Mutability>"))
+        .ident(&extension)
+        .code("<")
+        .tokens(a.clone())
+        .code(&format!(", {ext_m}> for ::generic_mutability::GenDyn<"))
+        .tokens(a.clone())
+        .code(&format!(", {ext_m}, dyn"))
+        .ident(&companion)
+        .code("+")
+        .tokens(object)
+        .code(">")
+        .group(Delimiter::Brace, |tokens| {
+            tokens.tokens(ext_impl);
+        });
+
+    Ok(output.into())
+}
@@ -0,0 +1,69 @@
+use proc_macro::{Delimiter, TokenStream};
+
+use crate::parse::{helper_flags, Error, Item, Result};
+use crate::tokens::Tokens;
+
+pub(crate) fn derive(input: TokenStream) -> Result<TokenStream> {
+    let item = Item::parse(input)?;
+
+    let m = item.generics.fresh_type_name("M");
+    let mut methods = Tokens::new();
+    for field in item.fields.iter() {
+        let mut skip = false;
+        for flag in helper_flags(&field.attrs, "gen_fields")? {
+            match flag.to_string().as_str() {
+                "skip" => skip = true,
+                _ => return Err(Error::new(flag.span(), "unknown flag, expected `skip`")),
+            }
+        }
+        if skip {
+            continue;
+        }
+
+        let name = field.name_string();
+        methods
+            .code("#[inline]")
+            .doc(&format!(
+                " Borrows the field `{name}` of a `GenRef` generically over mutability."
+            ))
+            .tokens(field.vis.clone())
+            .code("fn")
+            .new_ident(
+                &format!("gen_{name}"),
+                field.name.as_ref().map_or(field.span, |name| name.span()),
+            )
+            .code(&format!("<{m}: ::generic_mutability::Mutability>"))
+            .group(Delimiter::Parenthesis, |tokens| {
+                tokens.code(&format!(
+                    "this: ::generic_mutability::GenRef<'_, {m}, Self>"
+                ));
+            })
+            .code(&format!("-> ::generic_mutability::GenRef<'_, {m},"))
+            .tokens(field.ty.clone())
+            .code(">")
+            .group(Delimiter::Brace, |tokens| {
+                tokens.code("::generic_mutability::GenRef::map").group(
+                    Delimiter::Parenthesis,
+                    |tokens| {
+                        tokens
+                            .code("this, |this| &this.")
+                            .tokens(field.member())
+                            .code(", |this| &mut this.")
+                            .tokens(field.member());
+                    },
+                );
+            });
+    }
+
+    let mut output = Tokens::new();
+    output
+        .code("#[automatically_derived]")
+        .code("impl")
+        .tokens(item.generics.impl_generics(&[]))
+        .tokens(item.self_type())
+        .tokens(item.generics.where_clause(&[]))
+        .group(Delimiter::Brace, |tokens| {
+            tokens.tokens(methods);
+        });
+    Ok(output.into())
+}
@@ -0,0 +1,412 @@
+use proc_macro::{Delimiter, Ident, Span, TokenStream, TokenTree};
+
+use crate::parse::{
+    generic_arguments, parse_inputs, Attribute, Cursor, Error, FnArg, GenericParam, Generics,
+    Result,
+};
+use crate::tokens::{code, Tokens};
+
+/// A generic function defined by a pair of shared and mutable functions,
+/// `vis fn name<generics> = shared / mutable (inputs) -> output where predicates`.
+struct Definition {
+    attrs: Vec<Attribute>,
+    vis: TokenStream,
+    name: Ident,
+    generics: Generics,
+    shared: TokenStream,
+    mutable: TokenStream,
+    inputs: Vec<FnArg>,
+    output: Option<TokenStream>,
+}
+
+impl Definition {
+    /// Parses a definition, leaving the cursor after the terminating `;` (if any).
+    fn parse(cursor: &mut Cursor) -> Result<Definition> {
+        let attrs = cursor.attributes();
+        let vis = cursor.visibility();
+        if !cursor.keyword("fn") {
+            return Err(cursor.error("expected `fn`"));
+        }
+        let name = cursor
+            .ident()
+            .ok_or_else(|| cursor.error("expected an identifier"))?;
+        let params = cursor.generic_params()?;
+        if !cursor.punct('=') {
+            return Err(cursor.error("expected `=` followed by the shared and the mutable function, like `= Foo::get / Foo::get_mut`"));
+        }
+        let shared = path(cursor, true)?;
+        let mutable = path(cursor, false)?;
+        let inputs = cursor
+            .group(Delimiter::Parenthesis)
+            .ok_or_else(|| cursor.error("expected the parameters of the function"))?;
+        let output = cursor.return_type();
+        let where_predicates = cursor.where_predicates();
+        if !cursor.is_empty() && !cursor.punct(';') {
+            return Err(cursor.error("expected `;`"));
+        }
+        Ok(Definition {
+            attrs,
+            vis,
+            name,
+            generics: Generics {
+                params,
+                where_predicates,
+            },
+            shared,
+            mutable,
+            inputs: parse_inputs(inputs.stream()),
+            output,
+        })
+    }
+
+    /// The names of the parameters (other than the `GenRef`), for use in the body.
+    fn argument_names(&self) -> Vec<Ident> {
+        self.inputs
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                arg.binding()
+                    .unwrap_or_else(|| Ident::new(&format!("__arg{index}"), Span::call_site()))
+            })
+            .collect()
+    }
+
+    /// Emits the signature of the generic function, `fn name<generics>(receiver, inputs) -> output where ...`.
+    fn signature(
+        &self,
+        tokens: &mut Tokens,
+        generics: TokenStream,
+        receiver: TokenStream,
+        lifetime: &str,
+        m: &str,
+    ) {
+        tokens.code("fn").ident(&self.name).tokens(generics).group(
+            Delimiter::Parenthesis,
+            |tokens| {
+                tokens.tokens(receiver).code(",");
+                for (arg, name) in self.inputs.iter().zip(self.argument_names()) {
+                    tokens
+                        .ident(&name)
+                        .code(":")
+                        .tokens(arg.ty.clone().unwrap_or_default())
+                        .code(",");
+                }
+            },
+        );
+        if let Some(output) = &self.output {
+            tokens.code("->");
+            match optional_reference(output) {
+                Some(target) => {
+                    tokens
+                        .code(&format!(
+                            "::core::option::Option<::generic_mutability::GenRef<{lifetime}, {m},"
+                        ))
+                        .tokens(target)
+                        .code(">>");
+                }
+                None => {
+                    tokens
+                        .code(&format!("::generic_mutability::GenRef<{lifetime}, {m},"))
+                        .tokens(output.clone())
+                        .code(">");
+                }
+            }
+        }
+        tokens.tokens(self.generics.where_clause(&[]));
+    }
+
+    /// Emits the body of the generic function, dispatching on the mutability `m` of the `GenRef` called `this`.
+    fn body(&self, tokens: &mut Tokens, this: &str, m: &str) {
+        let arguments = self.argument_names();
+        tokens
+            .code(&format!(
+                "match <{m} as ::generic_mutability::Mutability>::mutability()"
+            ))
+            .group(Delimiter::Brace, |tokens| {
+                let branches = [
+                    ("Shared", &self.shared, "gen_into_shared", "gen_from_shared"),
+                    ("Mutable", &self.mutable, "gen_into_mut", "gen_from_mut"),
+                ];
+                for (variant, function, into, from) in branches {
+                    let mut call = Tokens::new();
+                    call.tokens(function.clone())
+                        .group(Delimiter::Parenthesis, |tokens| {
+                            tokens.code(&format!(
+                                "::generic_mutability::GenRef::{into}({this}, __proof),"
+                            ));
+                            for name in &arguments {
+                                tokens.ident(name).code(",");
+                            }
+                        });
+                    tokens
+                        .code(&format!(
+                            "::generic_mutability::MutabilityEnum::{variant}(__proof) =>"
+                        ))
+                        .group(Delimiter::Brace, |tokens| match &self.output {
+                            Some(output) if optional_reference(output).is_some() => {
+                                tokens.code("::core::option::Option::map").group(
+                                    Delimiter::Parenthesis,
+                                    |tokens| {
+                                        tokens.tokens(call).code(&format!(
+                                            ", |reference| ::generic_mutability::GenRef::{from}(reference, __proof)"
+                                        ));
+                                    },
+                                );
+                            }
+                            Some(_) => {
+                                tokens
+                                    .code(&format!("::generic_mutability::GenRef::{from}"))
+                                    .group(Delimiter::Parenthesis, |tokens| {
+                                        tokens.tokens(call).code(", __proof");
+                                    });
+                            }
+                            None => {
+                                tokens.tokens(call);
+                            }
+                        });
+                }
+            });
+    }
+}
+
+/// Collects a path to a function, up to the `/` separating the shared and the mutable one or the parameters.
+fn path(cursor: &mut Cursor, shared: bool) -> Result<TokenStream> {
+    let mut tokens = Vec::new();
+    while let Some(token) = cursor.peek() {
+        match token {
+            TokenTree::Punct(punct) if shared && punct.as_char() == '/' => break,
+            TokenTree::Group(group) if !shared && group.delimiter() == Delimiter::Parenthesis => {
+                break
+            }
+            _ => tokens.extend(cursor.next()),
+        }
+    }
+    if tokens.is_empty() || (shared && !cursor.punct('/')) {
+        return Err(cursor.error(
+            "expected the shared and the mutable function separated by `/`, like `Foo::get / Foo::get_mut`",
+        ));
+    }
+    Ok(tokens.into_iter().collect())
+}
+
+/// The type that a path to an associated function belongs to, like `Foo` in `Foo::get` or `[T]` in `<[T]>::first`.
+fn self_type(path: &TokenStream) -> Option<TokenStream> {
+    let mut tokens: Vec<TokenTree> = path.clone().into_iter().collect();
+    if !matches!(tokens.pop(), Some(TokenTree::Ident(_))) {
+        return None;
+    }
+    for _ in 0..2 {
+        if !matches!(tokens.pop(), Some(TokenTree::Punct(punct)) if punct.as_char() == ':') {
+            return None;
+        }
+    }
+    let qualified = matches!(tokens.first(), Some(TokenTree::Punct(punct)) if punct.as_char() == '<')
+        && matches!(tokens.last(), Some(TokenTree::Punct(punct)) if punct.as_char() == '>');
+    if qualified {
+        tokens.pop();
+        tokens.remove(0);
+        // `<T as Trait>` refers to the type `T`.
+        if let Some(position) = tokens
+            .iter()
+            .position(|token| matches!(token, TokenTree::Ident(ident) if ident.to_string() == "as"))
+        {
+            tokens.truncate(position);
+        }
+    }
+    (!tokens.is_empty()).then(|| tokens.into_iter().collect())
+}
+
+/// The type `T` if `output` is of the form `Option<&T>`.
+fn optional_reference(output: &TokenStream) -> Option<TokenStream> {
+    let [argument] = <[TokenStream; 1]>::try_from(generic_arguments(output, "Option")?).ok()?;
+    let mut cursor = Cursor::new(argument);
+    cursor.punct('&').then(|| cursor.rest())
+}
+
+/// Picks the names of the lifetime and the mutability parameter of the generated functions.
+fn fresh_names(generics: &[&Generics]) -> (String, String) {
+    let merged = Generics {
+        params: generics
+            .iter()
+            .flat_map(|generics| generics.params.iter().cloned())
+            .collect(),
+        where_predicates: TokenStream::new(),
+    };
+    (merged.fresh_lifetime_name("s"), merged.fresh_type_name("M"))
+}
+
+fn mutability_bound(m: &str) -> TokenStream {
+    code(&format!("{m}: ::generic_mutability::Mutability"))
+}
+
+pub(crate) fn expand_fn(input: TokenStream) -> Result<TokenStream> {
+    let mut cursor = Cursor::new(input);
+    let definition = Definition::parse(&mut cursor)?;
+    if !cursor.is_empty() {
+        return Err(cursor.error("unexpected tokens after the definition"));
+    }
+    let Some(target) = self_type(&definition.shared) else {
+        return Err(Error::new(
+            definition.name.span(),
+            "expected a path to an associated function, like `Foo::get`, to determine the type of the `GenRef`",
+        ));
+    };
+    let (lifetime, m) = fresh_names(&[&definition.generics]);
+
+    let mut output = Tokens::new();
+    for attr in &definition.attrs {
+        output.tokens(attr.tokens.clone());
+    }
+    output.code("#[inline]").tokens(definition.vis.clone());
+    let mut receiver = Tokens::new();
+    receiver
+        .code(&format!(
+            "this: ::generic_mutability::GenRef<{lifetime}, {m},"
+        ))
+        .tokens(target)
+        .code(">");
+    definition.signature(
+        &mut output,
+        definition.generics.extended(
+            code(&lifetime),
+            Some(mutability_bound(&m)),
+            GenericParam::declaration,
+        ),
+        receiver.into(),
+        &lifetime,
+        &m,
+    );
+    output.group(Delimiter::Brace, |tokens| {
+        definition.body(tokens, "this", &m)
+    });
+    Ok(output.into())
+}
+
+pub(crate) fn expand_trait(input: TokenStream) -> Result<TokenStream> {
+    let mut cursor = Cursor::new(input);
+    let attrs = cursor.attributes();
+    let vis = cursor.visibility();
+    if !cursor.keyword("trait") {
+        return Err(cursor.error("expected `trait`"));
+    }
+    let name = cursor
+        .ident()
+        .ok_or_else(|| cursor.error("expected an identifier"))?;
+    let params = cursor.generic_params()?;
+    if !cursor.keyword("for") {
+        return Err(cursor.error("expected `for` followed by the type of the `GenRef`"));
+    }
+    let mut target = Vec::new();
+    while !cursor.is_empty() && !cursor.is_ident("where") && !cursor.is_group(Delimiter::Brace) {
+        target.extend(cursor.next());
+    }
+    let target: TokenStream = target.into_iter().collect();
+    let generics = Generics {
+        params,
+        where_predicates: cursor.where_predicates(),
+    };
+    let body = cursor
+        .group(Delimiter::Brace)
+        .ok_or_else(|| cursor.error("expected the functions of the trait"))?;
+    if !cursor.is_empty() {
+        return Err(cursor.error("unexpected tokens after the trait"));
+    }
+    let mut definitions = Vec::new();
+    let mut body = Cursor::new(body.stream());
+    while !body.is_empty() {
+        let definition = Definition::parse(&mut body)?;
+        if !definition.vis.is_empty() {
+            return Err(Error::new(
+                definition.name.span(),
+                "functions of extension traits can not have a visibility",
+            ));
+        }
+        definitions.push(definition);
+    }
+
+    let mut all_generics = vec![&generics];
+    all_generics.extend(definitions.iter().map(|definition| &definition.generics));
+    let (lifetime, m) = fresh_names(&all_generics);
+    let seal = Ident::new(&format!("__seal_{name}"), name.span());
+    let trait_generics = generics.extended(
+        code(&lifetime),
+        Some(mutability_bound(&m)),
+        GenericParam::declaration,
+    );
+    let trait_arguments =
+        generics.extended(code(&lifetime), Some(code(&m)), GenericParam::argument);
+    let mut genref = Tokens::new();
+    genref
+        .code(&format!("::generic_mutability::GenRef<{lifetime}, {m},"))
+        .tokens(target)
+        .code(">");
+    let genref = TokenStream::from(genref);
+    let where_clause = generics.where_clause(&[]);
+
+    let mut output = Tokens::new();
+    output
+        .code("#[doc(hidden)] #[allow(non_snake_case)] mod")
+        .ident(&seal)
+        .code("{ pub trait Sealed {} }");
+    output
+        .code("impl")
+        .tokens(trait_generics.clone())
+        .ident(&seal)
+        .code("::Sealed for")
+        .tokens(genref.clone())
+        .tokens(where_clause.clone())
+        .code("{}");
+
+    for attr in &attrs {
+        output.tokens(attr.tokens.clone());
+    }
+    output
+        .tokens(vis)
+        .code("trait")
+        .ident(&name)
+        .tokens(trait_generics.clone())
+        .code(":")
+        .ident(&seal)
+        .code("::Sealed")
+        .tokens(where_clause.clone())
+        .group(Delimiter::Brace, |tokens| {
+            for definition in &definitions {
+                for attr in &definition.attrs {
+                    tokens.tokens(attr.tokens.clone());
+                }
+                definition.signature(
+                    tokens,
+                    definition.generics.impl_generics(&[]),
+                    code("self"),
+                    &lifetime,
+                    &m,
+                );
+                tokens.code(";");
+            }
+        });
+
+    output
+        .code("impl")
+        .tokens(trait_generics)
+        .ident(&name)
+        .tokens(trait_arguments)
+        .code("for")
+        .tokens(genref)
+        .tokens(where_clause)
+        .group(Delimiter::Brace, |tokens| {
+            for definition in &definitions {
+                tokens.code("#[inline]");
+                definition.signature(
+                    tokens,
+                    definition.generics.impl_generics(&[]),
+                    code("self"),
+                    &lifetime,
+                    &m,
+                );
+                tokens.group(Delimiter::Brace, |tokens| {
+                    definition.body(tokens, "self", &m)
+                });
+            }
+        });
+    Ok(output.into())
+}
@@ -0,0 +1,220 @@
+use proc_macro::{Delimiter, Ident, Span, TokenStream};
+
+use crate::parse::{
+    generic_arguments, genref_parts, mentions, replace_ident, split_commas, Cursor, Error,
+    Function, GenericParam, Result,
+};
+use crate::tokens::{code, Tokens};
+
+pub(crate) fn expand(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+    let names = split_commas(args, false)
+        .into_iter()
+        .map(|tokens| {
+            let mut cursor = Cursor::new(tokens);
+            cursor.ident().filter(|_| cursor.is_empty())
+        })
+        .collect::<Option<Vec<Ident>>>();
+    let (shared_name, mut_name, free) = match names.as_deref() {
+        Some([shared, mutable]) => (shared.clone(), mutable.clone(), false),
+        Some([shared, mutable, free]) if free.to_string() == "free" => {
+            (shared.clone(), mutable.clone(), true)
+        }
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "expected the names of the shared and the mutable variant, like `#[gen_mut_pair(get, get_mut)]`, optionally followed by `free`",
+            ));
+        }
+    };
+
+    let function = Function::parse(input.clone())?;
+    let Some(m) = function
+        .generics
+        .params
+        .iter()
+        .find_map(|param| match param {
+            GenericParam::Type { name, bounds } if mentions(bounds.clone(), "Mutability") => {
+                Some(name.to_string())
+            }
+            _ => None,
+        })
+    else {
+        return Err(Error::new(
+            function.name.span(),
+            "expected a type parameter bounded by `Mutability`",
+        ));
+    };
+    let Some((first, rest)) = function.inputs.split_first() else {
+        return Err(Error::new(
+            function.name.span(),
+            "expected a `GenRef<'_, M, T>` as the first parameter",
+        ));
+    };
+    let Some((lifetime, target)) = first.ty.as_ref().and_then(|ty| genref_parts(ty, &m)) else {
+        return Err(Error::new(
+            function.name.span(),
+            "expected a `GenRef<'_, M, T>` as the first parameter",
+        ));
+    };
+    let is_method = target.to_string() == "Self";
+    let first_name = match first.binding() {
+        Some(name) => name,
+        None if is_method => Ident::new("this", Span::call_site()),
+        None => {
+            return Err(Error::new(
+                function.name.span(),
+                "expected an identifier as the name of the first parameter",
+            ))
+        }
+    };
+
+    let mut output = Tokens::new();
+    output.tokens(input);
+    let variants = [
+        (
+            shared_name,
+            "",
+            "::generic_mutability::Shared",
+            "into_shared",
+            "from_shared",
+        ),
+        (
+            mut_name,
+            "mut",
+            "::generic_mutability::Mutable",
+            "into_mut",
+            "from_mut",
+        ),
+    ];
+    for (name, reference, mutability, into, from) in variants {
+        let mutability = code(mutability);
+        let replace = |tokens: TokenStream| replace_ident(tokens, &m, &mutability);
+        let params: Vec<TokenStream> = function
+            .generics
+            .params
+            .iter()
+            .filter(|param| param.type_name().is_none_or(|name| name.to_string() != m))
+            .map(|param| replace(param.declaration()))
+            .collect();
+        let arguments: Vec<Ident> = rest
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                arg.binding()
+                    .unwrap_or_else(|| Ident::new(&format!("__arg{index}"), Span::call_site()))
+            })
+            .collect();
+
+        // The return type and the conversion of the result into it.
+        let (return_type, convert) = match &function.output {
+            Some(ty) => {
+                let option =
+                    generic_arguments(ty, "Option").and_then(|arguments| match &arguments[..] {
+                        [inner] => genref_parts(inner, &m),
+                        _ => None,
+                    });
+                if let Some((lifetime, target)) = genref_parts(ty, &m) {
+                    let mut return_type = Tokens::new();
+                    return_type
+                        .code("&")
+                        .tokens(lifetime)
+                        .code(reference)
+                        .tokens(target);
+                    (
+                        Some(TokenStream::from(return_type)),
+                        code(&format!("::generic_mutability::GenRef::{into}(result)")),
+                    )
+                } else if let Some((lifetime, target)) = option {
+                    let mut return_type = Tokens::new();
+                    return_type
+                        .code("::core::option::Option<&")
+                        .tokens(lifetime)
+                        .code(reference)
+                        .tokens(target)
+                        .code(">");
+                    (
+                        Some(TokenStream::from(return_type)),
+                        code(&format!(
+                            "::core::option::Option::map(result, ::generic_mutability::GenRef::{into})"
+                        )),
+                    )
+                } else {
+                    (Some(replace(ty.clone())), code("result"))
+                }
+            }
+            None => (None, code("result")),
+        };
+
+        for attr in function.attrs.iter().filter(|attr| attr.is("doc")) {
+            output.tokens(attr.tokens.clone());
+        }
+        output
+            .code("#[inline]")
+            .tokens(function.vis.clone())
+            .tokens(function.qualifiers.clone())
+            .code("fn")
+            .ident(&name);
+        if !params.is_empty() {
+            output.code("<");
+            for param in params {
+                output.tokens(param).code(",");
+            }
+            output.code(">");
+        }
+        output.group(Delimiter::Parenthesis, |tokens| {
+            if is_method {
+                tokens
+                    .code("&")
+                    .tokens(lifetime.clone())
+                    .code(reference)
+                    .code("self");
+            } else {
+                tokens
+                    .ident(&first_name)
+                    .code(": &")
+                    .tokens(lifetime.clone())
+                    .code(reference)
+                    .tokens(target.clone());
+            }
+            tokens.code(",");
+            for (arg, name) in rest.iter().zip(&arguments) {
+                tokens
+                    .ident(name)
+                    .code(":")
+                    .tokens(replace(arg.ty.clone().unwrap_or_default()))
+                    .code(",");
+            }
+        });
+        if let Some(return_type) = return_type {
+            output.code("->").tokens(return_type);
+        }
+        output
+            .tokens(replace(function.generics.where_clause(&[])))
+            .group(Delimiter::Brace, |tokens| {
+                tokens.code("let result =");
+                if !free {
+                    tokens.code("Self::");
+                }
+                tokens
+                    .ident(&function.name)
+                    .group(Delimiter::Parenthesis, |tokens| {
+                        tokens
+                            .code(&format!("::generic_mutability::GenRef::{from}"))
+                            .group(Delimiter::Parenthesis, |tokens| {
+                                if is_method {
+                                    tokens.code("self");
+                                } else {
+                                    tokens.ident(&first_name);
+                                }
+                            })
+                            .code(",");
+                        for name in &arguments {
+                            tokens.ident(name).code(",");
+                        }
+                    })
+                    .code(";")
+                    .tokens(convert);
+            });
+    }
+    Ok(output.into())
+}
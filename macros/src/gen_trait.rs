@@ -0,0 +1,332 @@
+use proc_macro::{Delimiter, Ident, Span, TokenStream, TokenTree};
+
+use crate::parse::{
+    generic_arguments, Cursor, Error, FnArg, Function, GenericParam, Generics, Result,
+};
+use crate::tokens::{code, Tokens};
+
+/// A reference type, `&'a T` or `&'a mut T`.
+struct Reference {
+    lifetime: Option<TokenStream>,
+    mutable: bool,
+    target: TokenStream,
+}
+
+impl Reference {
+    fn parse(tokens: TokenStream) -> Option<Reference> {
+        let mut cursor = Cursor::new(tokens);
+        if !cursor.punct('&') {
+            return None;
+        }
+        let lifetime = cursor.is_punct('\'').then(|| {
+            [cursor.next(), cursor.next()]
+                .into_iter()
+                .flatten()
+                .collect()
+        });
+        let mutable = cursor.keyword("mut");
+        Some(Reference {
+            lifetime,
+            mutable,
+            target: cursor.rest(),
+        })
+    }
+}
+
+/// The return type of one of the methods of a pair, `&T` or `Option<&T>`.
+struct Output {
+    reference: Reference,
+    optional: bool,
+}
+
+impl Output {
+    fn parse(output: &Option<TokenStream>) -> Option<Output> {
+        let output = output.as_ref()?;
+        if let Some(reference) = Reference::parse(output.clone()) {
+            return Some(Output {
+                reference,
+                optional: false,
+            });
+        }
+        let [argument] = <[TokenStream; 1]>::try_from(generic_arguments(output, "Option")?).ok()?;
+        Some(Output {
+            reference: Reference::parse(argument)?,
+            optional: true,
+        })
+    }
+}
+
+/// A required method with a `&self` or `&mut self` receiver returning a reference, which can be part of a pair.
+struct Accessor {
+    function: Function,
+    receiver: Reference,
+    output: Output,
+    /// The tokens of the declaration, without the `;`.
+    declaration: TokenStream,
+}
+
+impl Accessor {
+    fn parse(function: Function, mut declaration: Vec<TokenTree>) -> Option<Accessor> {
+        if function.body.is_some() {
+            return None;
+        }
+        let receiver = Reference::parse(function.inputs.first()?.pattern.clone())?;
+        if receiver.target.to_string() != "self" {
+            return None;
+        }
+        let output = Output::parse(&function.output)?;
+        if output.reference.mutable != receiver.mutable {
+            return None;
+        }
+        declaration.pop();
+        Some(Accessor {
+            function,
+            receiver,
+            output,
+            declaration: declaration.into_iter().collect(),
+        })
+    }
+
+    /// The names of the parameters after the receiver.
+    fn argument_names(&self) -> Result<Vec<Ident>> {
+        self.function.inputs[1..]
+            .iter()
+            .map(|arg| {
+                arg.binding().ok_or_else(|| {
+                    Error::new(
+                        arg.pattern.clone().into_iter().next().map_or_else(Span::call_site, |token| token.span()),
+                        "the parameters of paired methods must be named to be forwarded to the generic method",
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Emits the declaration with a default implementation calling `generic`.
+    fn default_implementation(&self, tokens: &mut Tokens, generic: &Ident) -> Result<()> {
+        let (into, from) = if self.receiver.mutable {
+            ("into_mut", "from_mut")
+        } else {
+            ("into_shared", "from_shared")
+        };
+        let arguments = self.argument_names()?;
+        let mut call = Tokens::new();
+        call.code("Self::")
+            .ident(generic)
+            .group(Delimiter::Parenthesis, |tokens| {
+                tokens.code(&format!("::generic_mutability::GenRef::{from}(self),"));
+                for name in &arguments {
+                    tokens.ident(name).code(",");
+                }
+            });
+        tokens
+            .tokens(self.declaration.clone())
+            .group(Delimiter::Brace, |tokens| {
+                if self.output.optional {
+                    tokens.code("::core::option::Option::map").group(
+                        Delimiter::Parenthesis,
+                        |tokens| {
+                            tokens
+                                .tokens(call)
+                                .code(&format!(", ::generic_mutability::GenRef::{into}"));
+                        },
+                    );
+                } else {
+                    tokens
+                        .code(&format!("::generic_mutability::GenRef::{into}"))
+                        .group(Delimiter::Parenthesis, |tokens| {
+                            tokens.tokens(call);
+                        });
+                }
+            });
+        Ok(())
+    }
+}
+
+pub(crate) fn expand(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+    if let Some(token) = args.into_iter().next() {
+        return Err(Error::new(
+            token.span(),
+            "`gen_trait` does not take arguments",
+        ));
+    }
+    let mut tokens: Vec<TokenTree> = input.into_iter().collect();
+    let body = match tokens.pop() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "this macro can only be applied to traits",
+            ))
+        }
+    };
+    let header = Cursor::new(tokens.iter().cloned().collect());
+    let mut lookahead = header.clone();
+    lookahead.attributes();
+    lookahead.visibility();
+    lookahead.keyword("unsafe");
+    lookahead.keyword("auto");
+    if !lookahead.is_ident("trait") {
+        return Err(Error::new(
+            Span::call_site(),
+            "this macro can only be applied to traits",
+        ));
+    }
+
+    // Items of the trait: either kept as they are, or an accessor that may be paired.
+    enum Entry {
+        Tokens(TokenStream),
+        Accessor(Box<Accessor>),
+    }
+    let mut entries = Vec::new();
+    let mut items = Cursor::new(body.stream());
+    while !items.is_empty() {
+        let start = items.position();
+        if items.is_function() {
+            let function = Function::parse_from(&mut items)?;
+            let declaration = items.consumed_since(start);
+            match Accessor::parse(function, declaration.clone().into_iter().collect()) {
+                Some(accessor) => entries.push(Entry::Accessor(Box::new(accessor))),
+                None => entries.push(Entry::Tokens(declaration)),
+            }
+            continue;
+        }
+        while let Some(token) = items.next() {
+            let end = match &token {
+                TokenTree::Punct(punct) => punct.as_char() == ';',
+                TokenTree::Group(group) => group.delimiter() == Delimiter::Brace,
+                _ => false,
+            };
+            if end {
+                break;
+            }
+        }
+        entries.push(Entry::Tokens(items.consumed_since(start)));
+    }
+
+    let accessors: Vec<&Accessor> = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Accessor(accessor) => Some(&**accessor),
+            Entry::Tokens(_) => None,
+        })
+        .collect();
+    let mutable_pair = |shared: &Accessor| {
+        let name = format!("{}_mut", shared.function.name);
+        accessors.iter().copied().find(|accessor| {
+            accessor.receiver.mutable && accessor.function.name.to_string() == name
+        })
+    };
+    let is_paired = |accessor: &Accessor| {
+        if accessor.receiver.mutable {
+            let name = accessor.function.name.to_string();
+            name.strip_suffix("_mut").is_some_and(|shared| {
+                accessors.iter().any(|other| {
+                    !other.receiver.mutable && other.function.name.to_string() == shared
+                })
+            })
+        } else {
+            mutable_pair(accessor).is_some()
+        }
+    };
+
+    let mut rewritten = Tokens::new();
+    for entry in &entries {
+        match entry {
+            Entry::Tokens(tokens) => {
+                rewritten.tokens(tokens.clone());
+            }
+            Entry::Accessor(accessor) if !is_paired(accessor) => {
+                rewritten.tokens(accessor.declaration.clone()).code(";");
+            }
+            Entry::Accessor(accessor) => {
+                let name = accessor.function.name.to_string();
+                let base = match accessor.receiver.mutable {
+                    true => name.strip_suffix("_mut").unwrap_or(&name),
+                    false => &name,
+                };
+                let generic = Ident::new(
+                    &format!("gen_{}", base.trim_start_matches("r#")),
+                    accessor.function.name.span(),
+                );
+                accessor.default_implementation(&mut rewritten, &generic)?;
+                if !accessor.receiver.mutable {
+                    generic_declaration(&mut rewritten, accessor, &generic);
+                }
+            }
+        }
+    }
+
+    let mut output = Tokens::new();
+    output
+        .tokens(tokens.into_iter().collect::<TokenStream>())
+        .group(Delimiter::Brace, |tokens| {
+            tokens.tokens(rewritten);
+        });
+    Ok(output.into())
+}
+
+/// Emits the declaration of the required generic method for the pair of `shared`.
+fn generic_declaration(tokens: &mut Tokens, shared: &Accessor, generic: &Ident) {
+    let function = &shared.function;
+    let m = function.generics.fresh_type_name("M");
+    let generics = Generics {
+        params: function.generics.params.clone(),
+        where_predicates: TokenStream::new(),
+    };
+    let name = &function.name;
+    tokens.doc(&format!(
+        " The generically mutable version of [`{name}`](Self::{name}) and [`{name}_mut`](Self::{name}_mut), which are implemented in terms of this method."
+    ));
+    let bound = code(&format!("{m}: ::generic_mutability::Mutability"));
+    // An elided lifetime would be ambiguous with other reference parameters, so the receiver and the output get a named one.
+    let (lifetime, generics) = match &shared.receiver.lifetime {
+        Some(lifetime) => (lifetime.clone(), generics.impl_generics(&[bound])),
+        None => {
+            let lifetime = code(&function.generics.fresh_lifetime_name("s"));
+            let generics =
+                generics.extended(lifetime.clone(), Some(bound), GenericParam::declaration);
+            (lifetime, generics)
+        }
+    };
+    let mut declaration = Tokens::new();
+    declaration
+        .code("fn")
+        .ident(generic)
+        .tokens(generics)
+        .group(Delimiter::Parenthesis, |tokens| {
+            tokens
+                .code("this: ::generic_mutability::GenRef<")
+                .tokens(lifetime.clone())
+                .code(&format!(", {m}, Self>,"));
+            for FnArg { pattern, ty } in &function.inputs[1..] {
+                tokens
+                    .tokens(pattern.clone())
+                    .code(":")
+                    .tokens(ty.clone().unwrap_or_default())
+                    .code(",");
+            }
+        })
+        .code("->");
+    let reference = &shared.output.reference;
+    let mut genref = Tokens::new();
+    genref
+        .code("::generic_mutability::GenRef<")
+        .tokens(reference.lifetime.clone().unwrap_or(lifetime))
+        .code(&format!(", {m},"))
+        .tokens(reference.target.clone())
+        .code(">");
+    if shared.output.optional {
+        declaration
+            .code("::core::option::Option<")
+            .tokens(genref)
+            .code(">");
+    } else {
+        declaration.tokens(genref);
+    }
+    declaration
+        .tokens(function.generics.where_clause(&[]))
+        .code(";");
+    // Diagnostics about the signature (like the trait not being dyn-compatible) point at the shared accessor.
+    tokens.tokens(declaration.with_span(name.span()));
+}
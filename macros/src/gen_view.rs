@@ -0,0 +1,321 @@
+use proc_macro::{Delimiter, Ident, Span, TokenStream, TokenTree};
+
+use crate::parse::{Error, Field, Fields, GenericParam, Item, Result};
+use crate::tokens::{code, Tokens};
+
+pub(crate) fn derive(input: TokenStream) -> Result<TokenStream> {
+    let item = Item::parse(input)?;
+    if item.fields.iter().next().is_none() {
+        return Err(Error::new(
+            item.name.span(),
+            "`GenView` can only be derived for structs with fields",
+        ));
+    }
+
+    let name = &item.name;
+    let view = Ident::new(&format!("{name}View"), name.span());
+    let lifetime = item.generics.fresh_lifetime_name("a");
+    let m = item.generics.fresh_type_name("M");
+    let generics = &item.generics;
+    let where_clause = generics.where_clause(&[]);
+
+    // The generics of the view, as declared and as used with a given lifetime and mutability.
+    let declaration = generics.extended(
+        code(&lifetime),
+        Some(code(&format!("{m}: ::generic_mutability::Mutability"))),
+        GenericParam::declaration,
+    );
+    // The generics of `impl` blocks for views with the given mutability, which is only a parameter if it is generic.
+    let impl_generics = |mutability: &str| {
+        if mutability == m {
+            declaration.clone()
+        } else {
+            generics.extended(code(&lifetime), None, GenericParam::declaration)
+        }
+    };
+    let view_type = |lifetime: &str, mutability: &str| {
+        let mut tokens = Tokens::new();
+        tokens.ident(&view).tokens(generics.extended(
+            code(lifetime),
+            Some(code(mutability)),
+            GenericParam::argument,
+        ));
+        TokenStream::from(tokens)
+    };
+    let self_type = item.self_type();
+
+    // Builds the view from an expression for each field, given its index and its name.
+    let construct = |field_value: &dyn Fn(usize, TokenStream) -> TokenStream| {
+        let mut tokens = Tokens::new();
+        tokens.ident(&view);
+        match &item.fields {
+            Fields::Named(fields) => {
+                tokens.group(Delimiter::Brace, |tokens| {
+                    for field in fields {
+                        tokens
+                            .tokens(field.member())
+                            .code(":")
+                            .tokens(field_value(field.index, field.member()))
+                            .code(",");
+                    }
+                });
+            }
+            Fields::Unnamed(fields) => {
+                tokens.group(Delimiter::Parenthesis, |tokens| {
+                    for field in fields {
+                        tokens
+                            .tokens(field_value(field.index, field.member()))
+                            .code(",");
+                    }
+                });
+            }
+            Fields::Unit => unreachable!(),
+        }
+        TokenStream::from(tokens)
+    };
+
+    let mut output = Tokens::new();
+
+    // The view struct.
+    output
+        .doc(&format!(
+            " A view of the fields of [`{name}`], each borrowed as a `GenRef` with the mutability `{m}`."
+        ))
+        .doc("")
+        .doc(&format!(" This is created by `{name}::gen_view`."))
+        .tokens(item.vis.clone())
+        .code("struct")
+        .ident(&view)
+        .tokens(declaration.clone());
+    let field_type = |field: &Field| {
+        let mut tokens = Tokens::new();
+        tokens
+            .code(&format!("::generic_mutability::GenRef<{lifetime}, {m},"))
+            .tokens(field.ty.clone())
+            .code(">");
+        TokenStream::from(tokens)
+    };
+    match &item.fields {
+        Fields::Named(fields) => {
+            output
+                .tokens(where_clause.clone())
+                .group(Delimiter::Brace, |tokens| {
+                    for field in fields {
+                        tokens
+                            .tokens(field.vis.clone())
+                            .tokens(field.member())
+                            .code(":")
+                            .tokens(field_type(field))
+                            .code(",");
+                    }
+                });
+        }
+        Fields::Unnamed(fields) => {
+            output
+                .group(Delimiter::Parenthesis, |tokens| {
+                    for field in fields {
+                        tokens
+                            .tokens(field.vis.clone())
+                            .tokens(field_type(field))
+                            .code(",");
+                    }
+                })
+                .tokens(where_clause.clone())
+                .code(";");
+        }
+        Fields::Unit => unreachable!(),
+    }
+
+    // `gen_view` on the original struct.
+    let members: Vec<TokenStream> = item.fields.iter().map(|field| field.member()).collect();
+    let bindings: Vec<Ident> = (0..members.len())
+        .map(|index| Ident::new(&format!("__field{index}"), Span::call_site()))
+        .collect();
+    let from_bindings = construct(&|index, _| TokenTree::Ident(bindings[index].clone()).into());
+    output
+        .code("#[automatically_derived] impl")
+        .tokens(generics.impl_generics(&[]))
+        .tokens(self_type.clone())
+        .tokens(where_clause.clone())
+        .group(Delimiter::Brace, |tokens| {
+            tokens
+                .code("#[inline]")
+                .doc(&format!(
+                    " Splits a `GenRef` to [`{name}`] into a [`{view}`] of all of its fields."
+                ))
+                .tokens(item.vis.clone())
+                .code(&format!(
+                    "fn gen_view<{lifetime}, {m}: ::generic_mutability::Mutability>"
+                ))
+                .group(Delimiter::Parenthesis, |tokens| {
+                    tokens.code(&format!(
+                        "this: ::generic_mutability::GenRef<{lifetime}, {m}, Self>"
+                    ));
+                })
+                .code("->")
+                .tokens(view_type(&lifetime, &m))
+                .group(Delimiter::Brace, |tokens| {
+                    tokens
+                        .code("let")
+                        .group(Delimiter::Parenthesis, |tokens| {
+                            for binding in &bindings {
+                                tokens.ident(binding).code(",");
+                            }
+                        })
+                        .code("= ::generic_mutability::fields!")
+                        .group(Delimiter::Parenthesis, |tokens| {
+                            tokens.code("&gen this =>");
+                            for member in &members {
+                                tokens.tokens(member.clone()).code(",");
+                            }
+                        })
+                        .code(";")
+                        .tokens(from_bindings);
+                });
+        });
+
+    // `reborrow` and `downgrade` on the view.
+    let reborrowed = construct(&|_, member| {
+        let mut tokens = Tokens::new();
+        tokens.code("::generic_mutability::GenRef::reborrow").group(
+            Delimiter::Parenthesis,
+            |tokens| {
+                tokens.code("&mut self.").tokens(member);
+            },
+        );
+        tokens.into()
+    });
+    let downgraded = construct(&|_, member| {
+        let mut tokens = Tokens::new();
+        tokens
+            .code("::generic_mutability::GenRef::from_shared")
+            .group(Delimiter::Parenthesis, |tokens| {
+                tokens
+                    .code("::generic_mutability::GenRef::gen_into_shared_downgrading")
+                    .group(Delimiter::Parenthesis, |tokens| {
+                        tokens.code("self.").tokens(member);
+                    });
+            });
+        tokens.into()
+    });
+    // The views implement `Reborrow` and `Downgrade`, so that they compose with other types holding `GenRef`s.
+    // The inherent methods forward to them, so that they can be called without importing the traits.
+    let reborrowed_lifetime = generics.fresh_lifetime_name("r");
+    output
+        .code("#[automatically_derived] impl")
+        .tokens(impl_generics(&m))
+        .code("::generic_mutability::Reborrow for")
+        .tokens(view_type(&lifetime, &m))
+        .tokens(where_clause.clone())
+        .group(Delimiter::Brace, |tokens| {
+            tokens
+                .code(&format!("type Reborrowed<{reborrowed_lifetime}> ="))
+                .tokens(view_type(&reborrowed_lifetime, &m))
+                .code(&format!("where Self: {reborrowed_lifetime};"))
+                .code("#[inline] fn reborrow(&mut self) -> Self::Reborrowed<'_>")
+                .group(Delimiter::Brace, |tokens| {
+                    tokens.tokens(reborrowed);
+                });
+        });
+    output
+        .code("#[automatically_derived] impl")
+        .tokens(impl_generics(&m))
+        .code("::generic_mutability::Downgrade for")
+        .tokens(view_type(&lifetime, &m))
+        .tokens(where_clause.clone())
+        .group(Delimiter::Brace, |tokens| {
+            tokens
+                .code("type Downgraded =")
+                .tokens(view_type(&lifetime, "::generic_mutability::Shared"))
+                .code("; #[inline] fn downgrade(self) -> Self::Downgraded")
+                .group(Delimiter::Brace, |tokens| {
+                    tokens.tokens(downgraded);
+                });
+        });
+    output
+        .code("#[automatically_derived] impl")
+        .tokens(impl_generics(&m))
+        .tokens(view_type(&lifetime, &m))
+        .tokens(where_clause.clone())
+        .group(Delimiter::Brace, |tokens| {
+            tokens
+                .code("#[inline]")
+                .doc(" Reborrows every field of the view, creating a shorter-lived view without consuming this one.")
+                .code("pub fn reborrow(&mut self) ->")
+                .tokens(view_type("'_", &m))
+                .group(Delimiter::Brace, |tokens| {
+                    tokens.code("::generic_mutability::Reborrow::reborrow(self)");
+                })
+                .code("#[inline]")
+                .doc(" Downgrades every field of the view into a shared reference.")
+                .code("pub fn downgrade(self) ->")
+                .tokens(view_type(&lifetime, "::generic_mutability::Shared"))
+                .group(Delimiter::Brace, |tokens| {
+                    tokens.code("::generic_mutability::Downgrade::downgrade(self)");
+                });
+        });
+
+    // Conversions from native references and between views.
+    let conversions = [
+        (
+            format!("&{lifetime}"),
+            "::generic_mutability::Shared",
+            "::generic_mutability::GenRef::from_shared(value)",
+        ),
+        (
+            format!("&{lifetime} mut"),
+            "::generic_mutability::Mutable",
+            "::generic_mutability::GenRef::from_mut(value)",
+        ),
+    ];
+    for (reference, mutability, genref) in conversions {
+        output
+            .code("#[automatically_derived] impl")
+            .tokens(impl_generics(mutability))
+            .code("::core::convert::From<")
+            .code(&reference)
+            .tokens(self_type.clone())
+            .code("> for")
+            .tokens(view_type(&lifetime, mutability))
+            .tokens(where_clause.clone())
+            .group(Delimiter::Brace, |tokens| {
+                tokens
+                    .code("#[inline] fn from")
+                    .group(Delimiter::Parenthesis, |tokens| {
+                        tokens
+                            .code("value:")
+                            .code(&reference)
+                            .tokens(self_type.clone());
+                    })
+                    .code("-> Self")
+                    .group(Delimiter::Brace, |tokens| {
+                        tokens.ident(name).code("::gen_view").group(
+                            Delimiter::Parenthesis,
+                            |tokens| {
+                                tokens.code(genref);
+                            },
+                        );
+                    });
+            });
+    }
+    output
+        .code("#[automatically_derived] impl")
+        .tokens(impl_generics("::generic_mutability::Shared"))
+        .code("::core::convert::From<")
+        .tokens(view_type(&lifetime, "::generic_mutability::Mutable"))
+        .code("> for")
+        .tokens(view_type(&lifetime, "::generic_mutability::Shared"))
+        .tokens(where_clause)
+        .group(Delimiter::Brace, |tokens| {
+            tokens
+                .code("#[inline] fn from")
+                .group(Delimiter::Parenthesis, |tokens| {
+                    tokens
+                        .code("value:")
+                        .tokens(view_type(&lifetime, "::generic_mutability::Mutable"));
+                })
+                .code("-> Self { value.downgrade() }");
+        });
+
+    Ok(output.into())
+}
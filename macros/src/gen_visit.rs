@@ -0,0 +1,403 @@
+use proc_macro::{Delimiter, Ident, Span, TokenStream, TokenTree};
+
+use crate::parse::{
+    generic_arguments, helper_flags, split_commas, Attribute, Cursor, Error, Field, Fields, Item,
+    Result,
+};
+use crate::tokens::Tokens;
+
+/// How the value of a field is visited.
+enum Shape {
+    /// A node type, visited with its `visit_*` method.
+    Node(Ident),
+    /// A `Vec`, whose items are visited.
+    Vec(Box<Shape>),
+    /// An `Option`, whose content is visited if present.
+    Option(Box<Shape>),
+    /// A `Box`, whose content is visited.
+    Box(Box<Shape>),
+}
+
+impl Shape {
+    fn parse(ty: &TokenStream, span: Span) -> Result<Shape> {
+        for (container, wrap) in [
+            ("Vec", Shape::Vec as fn(Box<Shape>) -> Shape),
+            ("Option", Shape::Option),
+            ("Box", Shape::Box),
+        ] {
+            if let Some(arguments) = generic_arguments(ty, container) {
+                if let Ok([argument]) = <[TokenStream; 1]>::try_from(arguments) {
+                    return Ok(wrap(Box::new(Shape::parse(&argument, span)?)));
+                }
+            }
+        }
+        // A path without generic arguments, like `Expr` or `ast::Expr`.
+        let mut cursor = Cursor::new(ty.clone());
+        if cursor.punct(':') {
+            cursor.punct(':');
+        }
+        let mut last = None;
+        while let Some(segment) = cursor.ident() {
+            last = Some(segment);
+            if !(cursor.punct(':') && cursor.punct(':')) {
+                break;
+            }
+        }
+        match last {
+            Some(name) if cursor.is_empty() => Ok(Shape::Node(name)),
+            _ => Err(Error::new(
+                span,
+                "expected a node type, or a `Vec`, `Option` or `Box` of one; fields of other types can be skipped with `#[gen_visit(skip)]`",
+            )),
+        }
+    }
+
+    /// Emits the statements visiting `value`, which is a `&T` or a `&mut T` depending on `reference`.
+    fn visit(
+        &self,
+        tokens: &mut Tokens,
+        value: TokenStream,
+        reference: &str,
+        gen_from: &str,
+        depth: usize,
+    ) {
+        let item = Ident::new(&format!("__item{depth}"), Span::call_site());
+        match self {
+            Shape::Node(name) => {
+                tokens
+                    .code("visitor.")
+                    .new_ident(
+                        &format!("visit_{}", snake_case(&name.to_string())),
+                        name.span(),
+                    )
+                    .group(Delimiter::Parenthesis, |tokens| {
+                        tokens
+                            .code(&format!("::generic_mutability::GenRef::{gen_from}"))
+                            .group(Delimiter::Parenthesis, |tokens| {
+                                tokens.tokens(value).code(", __proof");
+                            });
+                    })
+                    .code(";");
+            }
+            Shape::Vec(inner) => {
+                tokens
+                    .code("for")
+                    .ident(&item)
+                    .code("in")
+                    .tokens(value)
+                    .group(Delimiter::Brace, |tokens| {
+                        inner.visit(
+                            tokens,
+                            TokenTree::Ident(item.clone()).into(),
+                            reference,
+                            gen_from,
+                            depth + 1,
+                        );
+                    });
+            }
+            Shape::Option(inner) => {
+                tokens
+                    .code("if let ::core::option::Option::Some")
+                    .group(Delimiter::Parenthesis, |tokens| {
+                        tokens.ident(&item);
+                    })
+                    .code("=")
+                    .tokens(value)
+                    .group(Delimiter::Brace, |tokens| {
+                        inner.visit(
+                            tokens,
+                            TokenTree::Ident(item.clone()).into(),
+                            reference,
+                            gen_from,
+                            depth + 1,
+                        );
+                    });
+            }
+            Shape::Box(inner) => {
+                let mut deref = Tokens::new();
+                deref
+                    .code(&format!("&{reference} **"))
+                    .group(Delimiter::Parenthesis, |tokens| {
+                        tokens.tokens(value);
+                    });
+                inner.visit(tokens, deref.into(), reference, gen_from, depth);
+            }
+        }
+    }
+}
+
+/// Converts a type name like `BinaryOp` to `binary_op`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.trim_start_matches("r#").chars().collect();
+    let mut output = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_lower)
+            {
+                output.push('_');
+            }
+        }
+        output.extend(c.to_lowercase());
+    }
+    output
+}
+
+/// The fields that are visited, with their shapes.
+fn visited_fields(fields: &Fields) -> Result<Vec<(&Field, Shape)>> {
+    let mut visited = Vec::new();
+    for field in fields.iter() {
+        let mut skip = false;
+        for flag in helper_flags(&field.attrs, "gen_visit")? {
+            match flag.to_string().as_str() {
+                "skip" => skip = true,
+                _ => return Err(Error::new(flag.span(), "unknown flag, expected `skip`")),
+            }
+        }
+        if !skip {
+            visited.push((field, Shape::parse(&field.ty, field.span)?));
+        }
+    }
+    Ok(visited)
+}
+
+/// Emits `{ member: __0, .. } => { ... }` for the visited fields, the body of a pattern for `path`.
+fn destructure(
+    tokens: &mut Tokens,
+    path: TokenStream,
+    visited: &[(&Field, Shape)],
+    reference: &str,
+    gen_from: &str,
+    body: impl FnOnce(&mut Tokens, TokenStream, TokenStream),
+) {
+    let mut pattern = Tokens::new();
+    pattern.tokens(path).group(Delimiter::Brace, |tokens| {
+        for (index, (field, _)) in visited.iter().enumerate() {
+            tokens
+                .tokens(field.member())
+                .code(":")
+                .new_ident(&format!("__{index}"), Span::call_site())
+                .code(",");
+        }
+        tokens.code("..");
+    });
+    let mut statements = Tokens::new();
+    for (index, (_, shape)) in visited.iter().enumerate() {
+        let binding = Ident::new(&format!("__{index}"), Span::call_site());
+        shape.visit(
+            &mut statements,
+            TokenTree::Ident(binding).into(),
+            reference,
+            gen_from,
+            0,
+        );
+    }
+    body(tokens, pattern.into(), statements.into());
+}
+
+/// Parses `#[gen_visit(visitor)]` or `#[gen_visit(visitor(Other, Types))]` on the item.
+fn visitor_nodes(attrs: &[Attribute]) -> Result<Option<Vec<Ident>>> {
+    let mut nodes = None;
+    for attr in attrs.iter().filter(|attr| attr.is("gen_visit")) {
+        let Some(args) = &attr.args else {
+            return Err(Error::new(
+                attr.name.as_ref().map_or_else(Span::call_site, Ident::span),
+                "expected arguments, like `#[gen_visit(visitor(...))]`",
+            ));
+        };
+        for arg in split_commas(args.stream(), false) {
+            let mut cursor = Cursor::new(arg);
+            if !cursor.keyword("visitor") {
+                return Err(Error::new(
+                    args.span(),
+                    "unexpected argument in `#[gen_visit]`, expected `visitor(...)`",
+                ));
+            }
+            let nodes: &mut Vec<Ident> = nodes.get_or_insert_with(Vec::new);
+            if let Some(list) = cursor.group(Delimiter::Parenthesis) {
+                for node in split_commas(list.stream(), false) {
+                    let mut cursor = Cursor::new(node);
+                    match cursor.ident() {
+                        Some(ident) if cursor.is_empty() => nodes.push(ident),
+                        _ => return Err(Error::new(list.span(), "expected a list of type names")),
+                    }
+                }
+            }
+            if !cursor.is_empty() {
+                return Err(cursor.error("unexpected tokens after the list of types"));
+            }
+        }
+    }
+    Ok(nodes)
+}
+
+pub(crate) fn derive(input: TokenStream) -> Result<TokenStream> {
+    let item = Item::parse_with_enums(input)?;
+    if !item.generics.params.is_empty() {
+        return Err(Error::new(
+            item.name.span(),
+            "generic types are not supported by `GenVisit`",
+        ));
+    }
+    let name = &item.name;
+    let snake = snake_case(&name.to_string());
+
+    let children = match &item.variants {
+        None => !visited_fields(&item.fields)?.is_empty(),
+        Some(variants) => variants.iter().any(|variant| {
+            visited_fields(&variant.fields).is_ok_and(|visited| !visited.is_empty())
+        }),
+    };
+    let mut arms = Tokens::new();
+    for (reference, gen_into, gen_from, variant) in [
+        ("", "gen_into_shared", "gen_from_shared", "Shared"),
+        ("mut", "gen_into_mut", "gen_from_mut", "Mutable"),
+    ] {
+        let mut into = Tokens::new();
+        into.code(&format!(
+            "::generic_mutability::GenRef::{gen_into}(node, __proof)"
+        ));
+        let into = TokenStream::from(into);
+        let mut branch = Tokens::new();
+        match &item.variants {
+            None => {
+                let visited = visited_fields(&item.fields)?;
+                destructure(
+                    &mut branch,
+                    code_path(name, None),
+                    &visited,
+                    reference,
+                    gen_from,
+                    |tokens, pattern, statements| {
+                        tokens
+                            .code("let")
+                            .tokens(pattern)
+                            .code("=")
+                            .tokens(into.clone())
+                            .code(";")
+                            .tokens(statements);
+                    },
+                );
+            }
+            Some(variants) if variants.is_empty() => {
+                branch
+                    .code("match *")
+                    .tokens(into.clone())
+                    .group(Delimiter::Brace, |_| {});
+            }
+            Some(variants) => {
+                let mut cases = Tokens::new();
+                for variant in variants {
+                    let visited = visited_fields(&variant.fields)?;
+                    destructure(
+                        &mut cases,
+                        code_path(name, Some(&variant.name)),
+                        &visited,
+                        reference,
+                        gen_from,
+                        |tokens, pattern, statements| {
+                            tokens
+                                .tokens(pattern)
+                                .code("=>")
+                                .group(Delimiter::Brace, |tokens| {
+                                    tokens.tokens(statements);
+                                });
+                        },
+                    );
+                }
+                branch
+                    .code("match")
+                    .tokens(into.clone())
+                    .group(Delimiter::Brace, |tokens| {
+                        tokens.tokens(cases);
+                    });
+            }
+        }
+        arms.code(&format!(
+            "::generic_mutability::MutabilityEnum::{variant}(__proof) =>"
+        ))
+        .group(Delimiter::Brace, |tokens| {
+            tokens.tokens(branch);
+        });
+    }
+
+    let mut output = Tokens::new();
+    output
+        .doc(&format!(
+            " Visits the children of a `{name}` with `visitor`, generically over mutability."
+        ))
+        .doc("")
+        .doc(&format!(
+            " This is the default implementation of `GenVisitor::visit_{snake}`, which can be called from overriding implementations to continue the traversal."
+        ))
+        .tokens(item.vis.clone())
+        .code("fn")
+        .new_ident(&format!("walk_{snake}"), name.span())
+        .code("<M: ::generic_mutability::Mutability, V: GenVisitor<M> + ?::core::marker::Sized>")
+        .group(Delimiter::Parenthesis, |tokens| {
+            tokens
+                .code("visitor: &mut V, node: ::generic_mutability::GenRef<'_, M,")
+                .ident(name)
+                .code(">");
+        })
+        .group(Delimiter::Brace, |tokens| {
+            if !children {
+                tokens.code("let _ = visitor;");
+            }
+            tokens
+                .code("match <M as ::generic_mutability::Mutability>::mutability()")
+                .group(Delimiter::Brace, |tokens| {
+                    tokens.tokens(arms);
+                });
+        });
+
+    if let Some(others) = visitor_nodes(&item.attrs)? {
+        let mut methods = Tokens::new();
+        for node in [name].into_iter().chain(&others) {
+            let snake = snake_case(&node.to_string());
+            methods
+                .doc(&format!(
+                    " Visits a [`{node}`]. By default, its children are visited with [`walk_{snake}`]."
+                ))
+                .code("fn")
+                .new_ident(&format!("visit_{snake}"), node.span())
+                .group(Delimiter::Parenthesis, |tokens| {
+                    tokens
+                        .code("&mut self, node: ::generic_mutability::GenRef<'_, M,")
+                        .ident(node)
+                        .code(">");
+                })
+                .group(Delimiter::Brace, |tokens| {
+                    tokens
+                        .new_ident(&format!("walk_{snake}"), node.span())
+                        .code("(self, node)");
+                });
+        }
+        output
+            .doc(&format!(
+                " A visitor of the syntax tree rooted at [`{name}`], generically over mutability."
+            ))
+            .doc("")
+            .doc(" Every method visits a node and, by default, the children of the node. Override them to act on the nodes of interest.")
+            .tokens(item.vis.clone())
+            .code("trait GenVisitor<M: ::generic_mutability::Mutability>")
+            .group(Delimiter::Brace, |tokens| {
+                tokens.tokens(methods);
+            });
+    }
+
+    Ok(output.into())
+}
+
+/// `Name` or `Name::Variant`.
+fn code_path(name: &Ident, variant: Option<&Ident>) -> TokenStream {
+    let mut tokens = Tokens::new();
+    tokens.ident(name);
+    if let Some(variant) = variant {
+        tokens.code("::").ident(variant);
+    }
+    tokens.into()
+}
@@ -0,0 +1,263 @@
+use proc_macro::{Delimiter, Ident, Span, TokenStream, TokenTree};
+
+use crate::parse::{split_commas, Cursor, Error, Function, GenericParam, Result};
+use crate::tokens::Tokens;
+
+/// The sub-macros of `gen_mut!`. The body of a function is only wrapped in `gen_mut!` if it uses one of them.
+const SUB_MACROS: [&str; 5] = [
+    "into_gen",
+    "from_gen",
+    "switch_shared_mut",
+    "gen_either",
+    "from_either",
+];
+
+pub(crate) fn expand(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+    let m = match &split_commas(args, false)[..] {
+        [] => Ident::new("M", Span::call_site()),
+        [name] => {
+            let mut cursor = Cursor::new(name.clone());
+            match cursor.ident() {
+                Some(ident) if cursor.is_empty() => ident,
+                _ => return Err(Error::new(Span::call_site(), "expected the name of the mutability parameter, like `#[generic_mutability(M)]`")),
+            }
+        }
+        [_, second, ..] => {
+            return Err(Error::new(
+                second
+                    .clone()
+                    .into_iter()
+                    .next()
+                    .map_or_else(Span::call_site, |token| token.span()),
+                "expected a single mutability parameter",
+            ))
+        }
+    };
+
+    let mut cursor = Cursor::new(input.clone());
+    let mut lookahead = cursor.clone();
+    lookahead.attributes();
+    lookahead.visibility();
+    lookahead.keyword("unsafe");
+    if lookahead.is_ident("impl") {
+        return expand_impl(input, &m);
+    }
+    let function = Function::parse_from(&mut cursor)?;
+    if !cursor.is_empty() {
+        return Err(cursor.error("unexpected tokens after the function"));
+    }
+    Ok(rewrite_function(function, &m))
+}
+
+/// Rewrites every function in an `impl` block.
+fn expand_impl(input: TokenStream, m: &Ident) -> Result<TokenStream> {
+    let mut tokens: Vec<TokenTree> = input.into_iter().collect();
+    let body = match tokens.pop() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "this macro can only be applied to functions and `impl` blocks",
+            ))
+        }
+    };
+
+    let mut items = Cursor::new(body.stream());
+    let mut rewritten = Tokens::new();
+    while !items.is_empty() {
+        if items.is_function() {
+            rewritten.tokens(rewrite_function(Function::parse_from(&mut items)?, m));
+            continue;
+        }
+        // Other items are kept as they are.
+        while let Some(token) = items.next() {
+            let end = match &token {
+                TokenTree::Punct(punct) => punct.as_char() == ';',
+                TokenTree::Group(group) => group.delimiter() == Delimiter::Brace,
+                _ => false,
+            };
+            rewritten.tokens(TokenStream::from(token));
+            if end {
+                break;
+            }
+        }
+    }
+
+    let mut output = Tokens::new();
+    output
+        .tokens(tokens.into_iter().collect::<TokenStream>())
+        .group(Delimiter::Brace, |tokens| {
+            tokens.tokens(rewritten);
+        });
+    Ok(output.into())
+}
+
+/// Rewrites the `&gen<T>` types in the signature of the function, and wraps its body in `gen_mut!` if it uses the sub-macros.
+fn rewrite_function(function: Function, m: &Ident) -> TokenStream {
+    let mut found = false;
+    let inputs: Vec<TokenStream> = function
+        .inputs
+        .iter()
+        .map(|arg| {
+            let mut tokens = Tokens::new();
+            tokens.tokens(arg.pattern.clone());
+            if let Some(ty) = &arg.ty {
+                tokens
+                    .code(":")
+                    .tokens(rewrite_types(ty.clone(), m, &mut found));
+            }
+            tokens.into()
+        })
+        .collect();
+    let output = function
+        .output
+        .map(|output| rewrite_types(output, m, &mut found));
+
+    let declared = function.generics.params.iter().any(|param| {
+        param
+            .type_name()
+            .is_some_and(|name| name.to_string() == m.to_string())
+    });
+    let mut extra = Vec::new();
+    if found && !declared {
+        let mut bound = Tokens::new();
+        bound.code("::generic_mutability::Mutability");
+        let mut param = Tokens::new();
+        param.ident(m).code(":").tokens(bound.with_span(m.span()));
+        extra.push(param.into());
+    }
+    let params: Vec<TokenStream> = function
+        .generics
+        .params
+        .iter()
+        .map(GenericParam::declaration)
+        .chain(extra)
+        .collect();
+
+    let mut tokens = Tokens::new();
+    for attr in &function.attrs {
+        tokens.tokens(attr.tokens.clone());
+    }
+    tokens
+        .tokens(function.vis)
+        .tokens(function.qualifiers)
+        .code("fn")
+        .ident(&function.name);
+    if !params.is_empty() {
+        tokens.code("<");
+        for param in params {
+            tokens.tokens(param).code(",");
+        }
+        tokens.code(">");
+    }
+    tokens.group(Delimiter::Parenthesis, |tokens| {
+        for input in inputs {
+            tokens.tokens(input).code(",");
+        }
+    });
+    if let Some(output) = output {
+        tokens.code("->").tokens(output);
+    }
+    tokens.tokens(function.generics.where_clause(&[]));
+    match function.body {
+        Some(body) if (found || declared) && uses_sub_macros(body.stream()) => {
+            tokens.group(Delimiter::Brace, |tokens| {
+                tokens
+                    .code("::generic_mutability::gen_mut!")
+                    .group(Delimiter::Brace, |tokens| {
+                        tokens.ident(m).code("=>").tokens(TokenTree::Group(body));
+                    });
+            });
+        }
+        Some(body) => {
+            tokens.tokens(TokenTree::Group(body));
+        }
+        None => {
+            tokens.code(";");
+        }
+    }
+    tokens.into()
+}
+
+/// Replaces `&gen<T>` and `&'a gen<T>` (or `&r#gen<T>`) with `GenRef<'_, M, T>` and `GenRef<'a, M, T>`, setting `found` if there were any.
+fn rewrite_types(tokens: TokenStream, m: &Ident, found: &mut bool) -> TokenStream {
+    let mut cursor = Cursor::new(tokens);
+    let mut output = Tokens::new();
+    while let Some(token) = cursor.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '&' => {
+                let mut lookahead = cursor.clone();
+                let lifetime: Option<TokenStream> = lookahead.is_punct('\'').then(|| {
+                    [lookahead.next(), lookahead.next()]
+                        .into_iter()
+                        .flatten()
+                        .collect()
+                });
+                // `gen` is a reserved keyword since edition 2024, where it has to be written as `r#gen`.
+                let gen = lookahead
+                    .ident()
+                    .filter(|ident| matches!(ident.to_string().as_str(), "gen" | "r#gen"));
+                match gen {
+                    Some(gen) if lookahead.is_punct('<') => {
+                        let target = lookahead.angle_brackets().unwrap_or_default();
+                        cursor = lookahead;
+                        *found = true;
+                        // The generated tokens point at `gen`, so that errors about the type point at the user's `&gen`.
+                        let mut prefix = Tokens::new();
+                        prefix.code("::generic_mutability::GenRef<");
+                        if lifetime.is_none() {
+                            prefix.code("'_");
+                        }
+                        output.tokens(prefix.with_span(gen.span()));
+                        if let Some(lifetime) = lifetime {
+                            output.tokens(lifetime);
+                        }
+                        let mut separator = Tokens::new();
+                        separator.code(",").ident(m).code(",");
+                        let mut close = Tokens::new();
+                        close.code(">");
+                        output
+                            .tokens(separator.with_span(gen.span()))
+                            .tokens(rewrite_types(target, m, found))
+                            .tokens(close.with_span(gen.span()));
+                    }
+                    _ => {
+                        output.tokens(TokenStream::from(TokenTree::Punct(punct)));
+                    }
+                }
+            }
+            TokenTree::Group(group) => {
+                let mut new = proc_macro::Group::new(
+                    group.delimiter(),
+                    rewrite_types(group.stream(), m, found),
+                );
+                new.set_span(group.span());
+                output.tokens(TokenStream::from(TokenTree::Group(new)));
+            }
+            token => {
+                output.tokens(TokenStream::from(token));
+            }
+        }
+    }
+    output.into()
+}
+
+/// Checks whether the tokens invoke one of the sub-macros of `gen_mut!`.
+fn uses_sub_macros(tokens: TokenStream) -> bool {
+    let mut previous: Option<TokenTree> = None;
+    for token in tokens {
+        match &token {
+            TokenTree::Group(group) if uses_sub_macros(group.stream()) => return true,
+            TokenTree::Punct(punct) if punct.as_char() == '!' => {
+                if let Some(TokenTree::Ident(ident)) = &previous {
+                    if SUB_MACROS.contains(&ident.to_string().as_str()) {
+                        return true;
+                    }
+                }
+            }
+            _ => {}
+        }
+        previous = Some(token);
+    }
+    false
+}
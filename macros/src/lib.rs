@@ -0,0 +1,641 @@
+//! Procedural macros for the `generic-mutability` crate.
+//!
+//! These macros are re-exported by `generic-mutability` when its `macros` feature is enabled, and should be used through that crate.
+//! The generated code refers to items of `generic-mutability` by the absolute path `::generic_mutability`.
+
+extern crate proc_macro;
+
+mod downgrade;
+mod gen_dyn;
+mod gen_fields;
+mod gen_fn;
+mod gen_mut_pair;
+mod gen_trait;
+mod gen_view;
+mod gen_visit;
+mod generic_mutability;
+mod parse;
+mod reborrow;
+mod tokens;
+
+use proc_macro::TokenStream;
+
+use parse::Error;
+
+/// Generates a generically mutable accessor for every field of a struct.
+///
+/// For every field `foo: T`, an associated function
+///
+/// ```rust, ignore
+/// fn gen_foo<M: Mutability>(this: GenRef<'_, M, Self>) -> GenRef<'_, M, T>
+/// ```
+///
+/// is generated, with the same visibility as the field.
+/// The fields of tuple structs generate `gen_0`, `gen_1`, and so on.
+///
+/// Fields marked with `#[gen_fields(skip)]` are skipped.
+///
+/// ```rust
+/// use generic_mutability::{GenFields, GenRef, Mutability};
+///
+/// #[derive(GenFields)]
+/// struct Config {
+///     pub name: String,
+///     retries: u32,
+///     #[gen_fields(skip)]
+///     cache: Vec<u8>,
+/// }
+///
+/// fn gen_retries<M: Mutability>(config: GenRef<'_, M, Config>) -> GenRef<'_, M, u32> {
+///     Config::gen_retries(config)
+/// }
+///
+/// let mut config = Config { name: String::from("main"), retries: 3, cache: Vec::new() };
+/// *gen_retries(GenRef::from(&mut config)) += 1;
+/// assert_eq!(*Config::gen_name(GenRef::from(&config)), "main");
+/// assert_eq!(config.retries, 4);
+/// ```
+///
+/// Skipped fields get no accessor:
+///
+/// ```rust, compile_fail, E0599
+/// use generic_mutability::{GenFields, GenRef};
+///
+/// #[derive(GenFields)]
+/// struct Config {
+///     retries: u32,
+///     #[gen_fields(skip)]
+///     cache: Vec<u8>,
+/// }
+///
+/// let config = Config { retries: 3, cache: Vec::new() };
+/// Config::gen_cache(GenRef::from(&config));
+/// ```
+///
+/// The accessors of private fields are private:
+///
+/// ```rust, compile_fail, E0624
+/// mod inner {
+///     #[derive(generic_mutability::GenFields)]
+///     pub struct Secret {
+///         value: u32,
+///     }
+///
+///     impl Secret {
+///         pub fn new(value: u32) -> Secret {
+///             Secret { value }
+///         }
+///     }
+/// }
+///
+/// let secret = inner::Secret::new(1);
+/// inner::Secret::gen_value(generic_mutability::GenRef::from(&secret));
+/// ```
+#[proc_macro_derive(GenFields, attributes(gen_fields))]
+pub fn derive_gen_fields(input: TokenStream) -> TokenStream {
+    gen_fields::derive(input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Generates a view struct that borrows every field of a struct as a `GenRef`.
+///
+/// For a struct `Foo`, this generates
+///
+/// - a struct `FooView<'a, M: Mutability>` with the same fields (and visibility), where each field of type `T` is a `GenRef<'a, M, T>`,
+/// - an associated function `Foo::gen_view(this: GenRef<'a, M, Foo>) -> FooView<'a, M>`, which splits the reference into references to each field,
+/// - implementations of `Reborrow` and `Downgrade` for the view (also available as the inherent methods `reborrow(&mut self) -> FooView<'_, M>` and `downgrade(self) -> FooView<'a, Shared>`), so views can be fields of structs deriving these traits,
+/// - `From` implementations creating `FooView<'a, Shared>` from `&'a Foo`, and `FooView<'a, Mutable>` from `&'a mut Foo`, and downgrading `FooView<'a, Mutable>` into `FooView<'a, Shared>`.
+///
+/// If the struct has generic parameters, the view has the same parameters, after the lifetime and after the mutability parameter.
+///
+/// ```rust
+/// use generic_mutability::{GenRef, GenView, Mutability};
+///
+/// #[derive(GenView)]
+/// struct Inventory {
+///     items: Vec<String>,
+///     selected: usize,
+/// }
+///
+/// fn gen_selected<M: Mutability>(inventory: GenRef<'_, M, Inventory>) -> GenRef<'_, M, String> {
+///     let view = Inventory::gen_view(inventory);
+///     GenRef::map(view.items, |items| &items[*view.selected], |items| &mut items[*view.selected])
+/// }
+///
+/// let mut inventory = Inventory { items: vec![String::from("a"), String::from("b")], selected: 1 };
+/// gen_selected(GenRef::from(&mut inventory)).push('!');
+/// assert_eq!(*gen_selected(GenRef::from(&inventory)), "b!");
+///
+/// let mut view = InventoryView::from(&mut inventory);
+/// *view.reborrow().selected = 0;
+/// let view = view.downgrade();
+/// assert_eq!(*view.items, ["a", "b!"]);
+/// ```
+#[proc_macro_derive(GenView)]
+pub fn derive_gen_view(input: TokenStream) -> TokenStream {
+    gen_view::derive(input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Implements `Reborrow` for a struct holding references or `GenRef`s.
+///
+/// The struct must have a lifetime parameter. The first lifetime parameter is the one that is shortened by reborrowing:
+/// `Reborrowed<'r>` is the same struct with its first lifetime replaced by `'r`.
+///
+/// Every field whose type mentions that lifetime is reborrowed with `Reborrow::reborrow`, so it must also implement `Reborrow`.
+/// This is the case for `GenRef`, `&mut T`, `&T`, `Option`s of these, and other types with a derived implementation.
+/// The rest of the fields are cloned.
+///
+/// ```rust
+/// use generic_mutability::{GenRef, Mutability, Reborrow};
+///
+/// #[derive(Reborrow)]
+/// struct Context<'a, M: Mutability> {
+///     output: GenRef<'a, M, Vec<u32>>,
+///     names: &'a [&'a str],
+///     depth: usize,
+/// }
+///
+/// fn visit<M: Mutability>(mut context: Context<'_, M>, value: u32) {
+///     if context.depth < 2 {
+///         context.depth += 1;
+///         visit(context.reborrow(), value + 1);
+///         visit(context, value + 2);
+///     } else if let generic_mutability::MutabilityEnum::Mutable(proof) = M::mutability() {
+///         GenRef::gen_into_mut(context.output, proof).push(value);
+///     }
+/// }
+///
+/// let mut output = Vec::new();
+/// visit(Context { output: GenRef::from(&mut output), names: &[], depth: 0 }, 0);
+/// assert_eq!(output, [2, 3, 3, 4]);
+/// ```
+#[proc_macro_derive(Reborrow)]
+pub fn derive_reborrow(input: TokenStream) -> TokenStream {
+    reborrow::derive(input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Implements `Downgrade` for a struct with a mutability parameter.
+///
+/// The mutability parameter is the first type parameter bounded by `Mutability` in the generics of the struct
+/// (bounds in the `where` clause are not considered).
+/// `Downgraded` is the same struct with this parameter replaced by `Shared`.
+///
+/// Every field whose type mentions the mutability parameter is converted with `Downgrade::downgrade`,
+/// so it must implement `Downgrade` with the field type of the downgraded struct as `Downgraded`.
+/// This is the case for `GenRef`, and `Option`s, tuples and arrays of these, and other types with a derived implementation.
+/// The rest of the fields are moved.
+///
+/// ```rust
+/// use generic_mutability::{Downgrade, GenRef, Mutability, Shared};
+///
+/// #[derive(Downgrade)]
+/// struct Query<'a, M: Mutability> {
+///     best: Option<GenRef<'a, M, u32>>,
+///     others: [GenRef<'a, M, u32>; 2],
+///     visited: usize,
+/// }
+///
+/// let mut values = [3, 1, 2];
+/// let [best, first, second] = values.each_mut().map(GenRef::from);
+/// let mut query = Query { best: Some(best), others: [first, second], visited: 3 };
+/// **query.best.as_mut().unwrap() += 1;
+///
+/// let shared: Query<'_, Shared> = query.downgrade();
+/// assert_eq!(*shared.best.unwrap() + *shared.others[0], 5);
+/// assert_eq!(shared.visited, 3);
+/// ```
+#[proc_macro_derive(Downgrade)]
+pub fn derive_downgrade(input: TokenStream) -> TokenStream {
+    downgrade::derive(input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Generates a conventional pair of shared and mutable functions from a function that is generic over mutability.
+///
+/// `#[gen_mut_pair(get, get_mut)]` is applied to a function like
+///
+/// ```rust, ignore
+/// fn get_gen<M: Mutability>(this: GenRef<'_, M, Self>, ...) -> GenRef<'_, M, U>
+/// ```
+///
+/// and generates the wrappers
+///
+/// ```rust, ignore
+/// fn get(&self, ...) -> &U
+/// fn get_mut(&mut self, ...) -> &mut U
+/// ```
+///
+/// next to it, with the same visibility and documentation, so that the public API stays idiomatic for users who do not know about this crate.
+///
+/// The mutability parameter is the first type parameter bounded by `Mutability` in the generics of the function.
+/// The first parameter must be a `GenRef<'_, M, T>`: if `T` is `Self`, it becomes the receiver `&self` or `&mut self`, otherwise a `&T` or `&mut T` parameter.
+/// A return type of `GenRef<'_, M, U>` becomes `&U` or `&mut U` (and `Option<GenRef<'_, M, U>>` becomes `Option<&U>` or `Option<&mut U>`).
+/// Everywhere else (like in other parameters), `M` is replaced by `Shared` or `Mutable`.
+///
+/// The wrappers call the generic function as an associated function (`Self::get_gen`), as the attribute can not see whether it is inside an `impl` block.
+/// For a free function, add `free` to the arguments (`#[gen_mut_pair(get, get_mut, free)]`), so that it is called as `get_gen` instead.
+///
+/// ```rust
+/// use generic_mutability::{gen_mut_pair, GenRef, Mutability};
+///
+/// pub struct Grid {
+///     width: usize,
+///     cells: Vec<u8>,
+/// }
+///
+/// impl Grid {
+///     /// Returns the cell at the given position, if it is in the grid.
+///     #[gen_mut_pair(get, get_mut)]
+///     pub fn gen_get<M: Mutability>(this: GenRef<'_, M, Self>, x: usize, y: usize) -> Option<GenRef<'_, M, u8>> {
+///         if x >= this.width {
+///             return None;
+///         }
+///         let index = y * this.width + x;
+///         GenRef::try_map(this, |grid| grid.cells.get(index), |grid| grid.cells.get_mut(index))
+///     }
+/// }
+///
+/// let mut grid = Grid { width: 2, cells: vec![0; 4] };
+/// *grid.get_mut(1, 1).unwrap() = 5;
+/// assert_eq!(grid.get(1, 1), Some(&5));
+/// assert_eq!(grid.get(2, 0), None);
+/// ```
+#[proc_macro_attribute]
+pub fn gen_mut_pair(args: TokenStream, input: TokenStream) -> TokenStream {
+    gen_mut_pair::expand(args, input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Defines a function that is generic over mutability from an existing pair of shared and mutable functions.
+///
+/// This is the reverse of `gen_mut_pair`, and is mainly useful for types of other crates.
+///
+/// ```rust, ignore
+/// gen_fn!(pub fn gen_peek = Foo::peek / Foo::peek_mut (idx: usize) -> Item);
+/// ```
+///
+/// defines
+///
+/// ```rust, ignore
+/// pub fn gen_peek<'s, M: Mutability>(this: GenRef<'s, M, Foo>, idx: usize) -> GenRef<'s, M, Item>
+/// ```
+///
+/// which calls `Foo::peek` or `Foo::peek_mut` depending on `M`.
+///
+/// The type of the `GenRef` is the type that the shared function belongs to (`Foo` in `Foo::peek`, `[T]` in `<[T]>::first`).
+/// The functions must take a reference to it as their first argument, followed by the listed parameters.
+/// They must return a reference to the type after `->` (like `&Item` and `&mut Item`), which is returned as a `GenRef`.
+/// If the return type is written as `Option<&Item>`, they must return `Option<&Item>` and `Option<&mut Item>`, which is returned as an `Option<GenRef<'s, M, Item>>`.
+/// Without a return type, the functions must return `()`.
+///
+/// Attributes (like documentation) are applied to the generated function.
+/// Generic parameters and a `where` clause can be added like on a normal function:
+///
+/// ```rust
+/// use generic_mutability::{gen_fn, GenRef, Mutable, Shared};
+///
+/// gen_fn! {
+///     /// Returns the first element of the slice, if there is one.
+///     pub fn gen_first<T> = <[T]>::first / <[T]>::first_mut () -> Option<&T>
+/// }
+/// gen_fn!(fn gen_as_slice<T> = Vec::<T>::as_slice / Vec::<T>::as_mut_slice () -> [T]);
+///
+/// let mut values = vec![1, 2, 3];
+/// *gen_first::<Mutable, _>(GenRef::from(&mut values[..])).unwrap() = 10;
+/// gen_as_slice(GenRef::from(&mut values))[2] = 30;
+/// assert_eq!(*gen_as_slice::<Shared, _>(GenRef::from(&values)), [10, 2, 30]);
+/// ```
+#[proc_macro]
+pub fn gen_fn(input: TokenStream) -> TokenStream {
+    gen_fn::expand_fn(input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Defines a sealed extension trait on `GenRef<'s, M, Type>` with generic functions defined from pairs of shared and mutable functions.
+///
+/// The functions are defined like in `gen_fn`, but they take the `GenRef` as `self`, like the methods of `GenRefMethods`.
+/// The trait has the lifetime `'s` and the mutability `M` of the `GenRef` as its first generic parameters, followed by the ones declared on the trait.
+///
+/// ```rust
+/// use std::collections::VecDeque;
+/// use generic_mutability::{extension_trait, GenRef, Mutability};
+///
+/// extension_trait! {
+///     /// Generically mutable access to the ends of a `VecDeque`.
+///     pub trait GenVecDequeExt<T> for VecDeque<T> {
+///         fn gen_front = VecDeque::front / VecDeque::front_mut () -> Option<&T>;
+///         fn gen_back = VecDeque::back / VecDeque::back_mut () -> Option<&T>;
+///     }
+/// }
+///
+/// fn gen_ends<M: Mutability>(mut deque: GenRef<'_, M, VecDeque<u8>>) -> Option<(u8, u8)> {
+///     let front = *GenRef::reborrow(&mut deque).gen_front()?;
+///     let back = *deque.gen_back()?;
+///     Some((front, back))
+/// }
+///
+/// let mut deque = VecDeque::from([1, 2, 3]);
+/// *GenRef::from(&mut deque).gen_back().unwrap() = 4;
+/// assert_eq!(gen_ends(GenRef::from(&deque)), Some((1, 4)));
+/// ```
+#[proc_macro]
+pub fn extension_trait(input: TokenStream) -> TokenStream {
+    gen_fn::expand_trait(input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Rewrites `&gen<T>` types in the signatures of a function or of the functions of an `impl` block into `GenRef`s.
+///
+/// Every `&gen<T>` and `&'a gen<T>` in the types of the parameters and the return type is replaced by `GenRef<'_, M, T>` and `GenRef<'a, M, T>`,
+/// and a single mutability parameter `M: Mutability` is added to the generics of the function.
+/// The mutability parameter can be given another name with `#[generic_mutability(N)]`. If the function already declares a generic parameter with that name, it is used instead of adding a new one.
+///
+/// If the body of the function uses the sub-macros of `gen_mut!` (like `from_gen!` and `into_gen!`), it is wrapped in `gen_mut! { M => ... }`, so they can be used directly.
+///
+/// Types generated from `&gen` point at the `gen` token, so type errors involving them are reported there.
+///
+/// Note that `&gen T` (without the angle brackets) can not be supported: it is not valid Rust syntax, so it is rejected by the parser before the attribute is applied.
+/// For the same reason, `&gen<Self>` can not be used as a receiver (`self`), but it can be used as the type of a normal parameter, like `this: &gen<Self>`.
+///
+/// Since edition 2024, `gen` is a reserved keyword, so `&gen<T>` is rejected by the parser as well.
+/// In that edition, write the raw identifier `&r#gen<T>` instead (this spelling is accepted in every edition).
+/// The `&gen $place` syntax of `into_gen!` is not affected, as macro arguments are not parsed as types:
+///
+/// ```rust, edition2024
+/// use generic_mutability::{generic_mutability, GenRef};
+///
+/// #[generic_mutability]
+/// fn gen_first<'a, T>(slice: &'a r#gen<[T]>) -> Option<&'a r#gen<T>> {
+///     let slice = from_gen!(slice);
+///     if slice.is_empty() {
+///         return None;
+///     }
+///     Some(into_gen!(&gen slice[0]))
+/// }
+///
+/// let mut values = [1, 2];
+/// *gen_first(GenRef::from(&mut values[..])).unwrap() += 10;
+/// assert_eq!(gen_first(GenRef::from(&values[..])).map(|first| *first), Some(11));
+/// ```
+///
+/// ```rust
+/// use generic_mutability::{generic_mutability, GenRef, Mutability};
+///
+/// struct Tree {
+///     value: i32,
+///     children: Vec<Tree>,
+/// }
+///
+/// #[generic_mutability]
+/// impl Tree {
+///     fn gen_child(this: &gen<Self>, index: usize) -> Option<&gen<Tree>> {
+///         let tree = from_gen!(this);
+///         switch_shared_mut![<[_]>::get, <[_]>::get_mut](switch_shared_mut![&tree.children, &mut tree.children], index)
+///             .map(into_gen!())
+///     }
+///
+///     fn gen_leftmost<'a>(mut this: &'a gen<Self>) -> &'a gen<i32> {
+///         while !this.children.is_empty() {
+///             this = Tree::gen_child(this, 0).unwrap();
+///         }
+///         GenRef::map(this, |tree| &tree.value, |tree| &mut tree.value)
+///     }
+/// }
+///
+/// let leaf = |value| Tree { value, children: Vec::new() };
+/// let mut tree = Tree { value: 1, children: vec![Tree { value: 2, children: vec![leaf(3)] }, leaf(4)] };
+/// *Tree::gen_leftmost(GenRef::from(&mut tree)) += 10;
+/// assert_eq!(*Tree::gen_leftmost(GenRef::from(&tree)), 13);
+/// assert_eq!(Tree::gen_child(GenRef::from(&tree), 1).map(|child| child.value), Some(4));
+/// ```
+#[proc_macro_attribute]
+pub fn generic_mutability(args: TokenStream, input: TokenStream) -> TokenStream {
+    generic_mutability::expand(args, input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Collapses pairs of shared and mutable accessor methods of a trait into a single required method that is generic over mutability.
+///
+/// For every pair of required methods like
+///
+/// ```rust, ignore
+/// fn node(&self, ...) -> &Node;
+/// fn node_mut(&mut self, ...) -> &mut Node;
+/// ```
+///
+/// a required method
+///
+/// ```rust, ignore
+/// fn gen_node<'s, M: Mutability>(this: GenRef<'s, M, Self>, ...) -> GenRef<'s, M, Node>;
+/// ```
+///
+/// is added to the trait, and the original methods get default implementations calling it.
+/// If the receiver lifetime is elided, the generic method names it `'s`, so that other reference parameters do not make the output lifetime ambiguous.
+/// This way, implementors only write the accessor once, and existing callers of the accessors keep working on concrete types and in generic code.
+///
+/// The generic method makes the trait dyn-incompatible, so `dyn Trait` no longer compiles.
+/// For use with trait objects, place `#[gen_dyn]` below this attribute, and use `dyn TraitDyn` instead:
+///
+/// ```rust, compile_fail, E0038
+/// use generic_mutability::gen_trait;
+///
+/// #[gen_trait]
+/// trait HasName {
+///     fn name(&self) -> &String;
+///     fn name_mut(&mut self) -> &mut String;
+/// }
+///
+/// fn print(named: &dyn HasName) {
+///     println!("{}", named.name());
+/// }
+/// ```
+///
+/// ```rust
+/// use generic_mutability::{gen_dyn, gen_trait};
+///
+/// #[gen_trait]
+/// #[gen_dyn]
+/// trait HasName {
+///     fn name(&self) -> &String;
+///     fn name_mut(&mut self) -> &mut String;
+/// }
+///
+/// fn print(named: &dyn HasNameDyn) {
+///     println!("{}", HasName::name(named));
+/// }
+/// ```
+///
+/// A pair consists of a method `x` taking `&self` and returning `&T` (or `Option<&T>`), and a method `x_mut` taking `&mut self` and returning `&mut T` (or `Option<&mut T>`).
+/// Both must be required methods (without a default implementation), and their other parameters must be named.
+/// The generated method has the generic parameters, the other parameters and the `where` clause of `x`.
+/// Other items of the trait are kept as they are.
+///
+/// ```rust
+/// use generic_mutability::{gen_trait, GenRef, Mutability};
+///
+/// #[gen_trait]
+/// trait Graph {
+///     fn node(&self, index: usize) -> Option<&String>;
+///     fn node_mut(&mut self, index: usize) -> Option<&mut String>;
+///
+///     fn len(&self) -> usize;
+/// }
+///
+/// struct Path(Vec<String>);
+///
+/// impl Graph for Path {
+///     fn gen_node<M: Mutability>(this: GenRef<'_, M, Self>, index: usize) -> Option<GenRef<'_, M, String>> {
+///         GenRef::try_map(this, |path| path.0.get(index), |path| path.0.get_mut(index))
+///     }
+///
+///     fn len(&self) -> usize {
+///         self.0.len()
+///     }
+/// }
+///
+/// let mut path = Path(vec!["a".to_string(), "b".to_string()]);
+/// path.node_mut(1).unwrap().push('c');
+/// assert_eq!(path.node(1).map(String::as_str), Some("bc"));
+/// assert_eq!((path.node(2), path.len()), (None, 2));
+/// ```
+#[proc_macro_attribute]
+pub fn gen_trait(args: TokenStream, input: TokenStream) -> TokenStream {
+    gen_trait::expand(args, input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Generates a dyn-compatible companion for a trait with methods that are generic over mutability.
+///
+/// Methods like `fn gen_x<M: Mutability>(this: GenRef<'_, M, Self>, ...)` make a trait dyn-incompatible.
+/// For a trait `Trait`, this attribute generates:
+///
+/// - a dyn-compatible trait `TraitDyn` with the same supertraits, which has the entries `gen_x_shared(&self, ...)` and `gen_x_mut(&mut self, ...)` for every generic method, and the other required methods as they are,
+/// - an implementation of `TraitDyn` for every (sized) type implementing `Trait`,
+/// - an implementation of `Trait` for `dyn TraitDyn`, where the generic methods dispatch to the shared or the mutable entry depending on `M`,
+/// - an extension trait `TraitGenDyn<'a, M>` implemented for `GenDyn<'a, M, dyn TraitDyn>`, which has the generic methods with `self` as the receiver.
+///
+/// The generic methods must take a `GenRef<'_, M, Self>` as their first parameter and return `GenRef<'_, M, T>` or `Option<GenRef<'_, M, T>>`, and `M` must not appear anywhere else.
+/// Apart from `M`, they can only have lifetime parameters.
+/// The other required methods must take `&self` or `&mut self`, and the parameters of all required methods must be named.
+/// Provided methods are not part of `TraitDyn`, but they are available on `dyn TraitDyn` through its implementation of `Trait`.
+/// Generic traits, associated types and associated constants are not supported.
+///
+/// `#[gen_trait]` can be placed above this attribute, so that the accessor pairs it generates the generic methods for are also available on trait objects.
+///
+/// ```rust
+/// use generic_mutability::{gen_dyn, GenDyn, GenRef, Mutability};
+///
+/// #[gen_dyn]
+/// trait Plugin {
+///     fn gen_setting<'s, M: Mutability>(this: GenRef<'s, M, Self>, key: &str) -> Option<GenRef<'s, M, String>>;
+///     fn name(&self) -> &str;
+/// }
+///
+/// struct Greeter {
+///     greeting: String,
+/// }
+///
+/// impl Plugin for Greeter {
+///     fn gen_setting<'s, M: Mutability>(this: GenRef<'s, M, Self>, key: &str) -> Option<GenRef<'s, M, String>> {
+///         match key {
+///             "greeting" => Some(GenRef::map(this, |greeter| &greeter.greeting, |greeter| &mut greeter.greeting)),
+///             _ => None,
+///         }
+///     }
+///
+///     fn name(&self) -> &str {
+///         "greeter"
+///     }
+/// }
+///
+/// let mut plugins: Vec<Box<dyn PluginDyn>> = vec![Box::new(Greeter { greeting: "hello".to_string() })];
+/// for plugin in &mut plugins {
+///     if let Some(mut greeting) = GenDyn::from(&mut **plugin).gen_setting("greeting") {
+///         greeting.push('!');
+///     }
+/// }
+///
+/// let greeter = GenDyn::from(&*plugins[0]);
+/// assert_eq!(greeter.name(), "greeter");
+/// assert_eq!(greeter.gen_setting("greeting").as_deref().map(String::as_str), Some("hello!"));
+/// ```
+#[proc_macro_attribute]
+pub fn gen_dyn(args: TokenStream, input: TokenStream) -> TokenStream {
+    gen_dyn::expand(args, input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Generates a visitor of a syntax tree, generically over mutability, like `syn`'s `Visit` and `VisitMut` in one.
+///
+/// For every type `Node` deriving `GenVisit`, a function
+///
+/// ```rust, ignore
+/// fn walk_node<M: Mutability, V: GenVisitor<M> + ?Sized>(visitor: &mut V, node: GenRef<'_, M, Node>);
+/// ```
+///
+/// is generated next to the type, with the visibility of the type.
+/// It calls the `visit_*` method of `visitor` for every field of the node (or of its variant, for enums), recursing through `Vec`, `Option` and `Box`.
+/// The method is named after the type of the field in snake case, like `visit_binary_op` for `BinaryOp`.
+/// Fields of other types (like `String` or `u32`) have to be skipped with `#[gen_visit(skip)]`.
+///
+/// The trait `GenVisitor<M>` is generated by the root of the tree, which is marked with `#[gen_visit(visitor(Other, Nodes))]`, listing the node types other than itself.
+/// It has a method `fn visit_node(&mut self, node: GenRef<'_, M, Node>)` for each of them, which calls `walk_node` by default.
+/// The `walk_*` functions refer to `GenVisitor` by name, so it must be in scope where the nodes are defined.
+/// Generic node types are not supported.
+///
+/// A visitor overrides the methods of the nodes it is interested in, and calls the `walk_*` function to continue with the children.
+/// A visitor that implements `GenVisitor<M>` for every `M` works on both shared and mutable references to the tree.
+///
+/// ```rust
+/// use generic_mutability::{GenRef, GenVisit, Mutability, MutabilityEnum, Shared};
+///
+/// #[derive(GenVisit)]
+/// #[gen_visit(visitor(Stmt, Expr))]
+/// struct Block {
+///     stmts: Vec<Stmt>,
+/// }
+///
+/// #[derive(GenVisit)]
+/// enum Stmt {
+///     Let(#[gen_visit(skip)] String, Option<Expr>),
+///     Expr(Expr),
+///     Block(Box<Block>),
+/// }
+///
+/// #[derive(GenVisit)]
+/// enum Expr {
+///     Literal(#[gen_visit(skip)] i64),
+///     Add(Box<Expr>, Box<Expr>),
+/// }
+///
+/// struct Doubler;
+///
+/// impl<M: Mutability> GenVisitor<M> for Doubler {
+///     fn visit_expr(&mut self, mut node: GenRef<'_, M, Expr>) {
+///         if let MutabilityEnum::Mutable(proof) = M::mutability() {
+///             if let Expr::Literal(value) = GenRef::gen_into_mut(GenRef::reborrow(&mut node), proof) {
+///                 *value *= 2;
+///             }
+///         }
+///         walk_expr(self, node);
+///     }
+/// }
+///
+/// struct Sum(i64);
+///
+/// impl GenVisitor<Shared> for Sum {
+///     fn visit_expr(&mut self, node: GenRef<'_, Shared, Expr>) {
+///         if let Expr::Literal(value) = &*node {
+///             self.0 += value;
+///         }
+///         walk_expr(self, node);
+///     }
+/// }
+///
+/// let mut block = Block {
+///     stmts: vec![
+///         Stmt::Let("x".to_string(), Some(Expr::Add(Box::new(Expr::Literal(1)), Box::new(Expr::Literal(2))))),
+///         Stmt::Block(Box::new(Block { stmts: vec![Stmt::Expr(Expr::Literal(3))] })),
+///     ],
+/// };
+/// Doubler.visit_block(GenRef::from(&mut block));
+/// let mut sum = Sum(0);
+/// sum.visit_block(GenRef::from(&block));
+/// assert_eq!(sum.0, 12);
+/// ```
+#[proc_macro_derive(GenVisit, attributes(gen_visit))]
+pub fn derive_gen_visit(input: TokenStream) -> TokenStream {
+    gen_visit::derive(input).unwrap_or_else(Error::into_compile_error)
+}
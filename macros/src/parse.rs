@@ -0,0 +1,1011 @@
+//! A minimal parser for the items that the macros of this crate are applied to.
+//!
+//! Only the structure of the items is parsed (attributes, visibility, generics, fields and function signatures).
+//! Types, bounds and expressions are kept as token streams, so their spans are preserved in the generated code.
+
+use proc_macro::{Delimiter, Group, Ident, Spacing, Span, TokenStream, TokenTree};
+
+use crate::tokens::Tokens;
+
+/// An error reported as a `compile_error!` at the given span.
+pub(crate) struct Error {
+    span: Span,
+    message: String,
+}
+
+impl Error {
+    pub(crate) fn new(span: Span, message: impl Into<String>) -> Self {
+        Error {
+            span,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn into_compile_error(self) -> TokenStream {
+        let mut tokens = Tokens::new();
+        tokens
+            .code("::core::compile_error!")
+            .group(Delimiter::Parenthesis, |tokens| {
+                tokens.string(&self.message);
+            });
+        tokens.code(";");
+        tokens.with_span(self.span)
+    }
+}
+
+pub(crate) type Result<T> = core::result::Result<T, Error>;
+
+/// An outer attribute, `#[path args]`.
+pub(crate) struct Attribute {
+    /// The path of the attribute, if it is a single identifier.
+    pub(crate) name: Option<Ident>,
+    /// The parenthesized arguments following the path.
+    pub(crate) args: Option<Group>,
+    /// The whole attribute, including the `#`.
+    pub(crate) tokens: TokenStream,
+}
+
+impl Attribute {
+    pub(crate) fn is(&self, name: &str) -> bool {
+        self.name
+            .as_ref()
+            .is_some_and(|ident| ident.to_string() == name)
+    }
+}
+
+/// A generic parameter. The bounds do not include defaults.
+#[derive(Clone)]
+pub(crate) enum GenericParam {
+    Lifetime {
+        name: TokenStream,
+        bounds: TokenStream,
+    },
+    Type {
+        name: Ident,
+        bounds: TokenStream,
+    },
+    Const {
+        name: Ident,
+        ty: TokenStream,
+    },
+}
+
+impl GenericParam {
+    /// The parameter as it appears in the arguments of the type (without bounds).
+    pub(crate) fn argument(&self) -> TokenStream {
+        match self {
+            GenericParam::Lifetime { name, .. } => name.clone(),
+            GenericParam::Type { name, .. } | GenericParam::Const { name, .. } => {
+                TokenTree::Ident(name.clone()).into()
+            }
+        }
+    }
+
+    /// The parameter as it appears in the generics of an `impl` block (with bounds, without defaults).
+    pub(crate) fn declaration(&self) -> TokenStream {
+        let mut tokens = Tokens::new();
+        match self {
+            GenericParam::Lifetime { name, bounds } => {
+                tokens.tokens(name.clone());
+                if !bounds.is_empty() {
+                    tokens.code(":").tokens(bounds.clone());
+                }
+            }
+            GenericParam::Type { name, bounds } => {
+                tokens.ident(name);
+                if !bounds.is_empty() {
+                    tokens.code(":").tokens(bounds.clone());
+                }
+            }
+            GenericParam::Const { name, ty } => {
+                tokens
+                    .code("const")
+                    .ident(name)
+                    .code(":")
+                    .tokens(ty.clone());
+            }
+        }
+        tokens.into()
+    }
+
+    pub(crate) fn is_lifetime(&self) -> bool {
+        matches!(self, GenericParam::Lifetime { .. })
+    }
+
+    pub(crate) fn type_name(&self) -> Option<&Ident> {
+        match self {
+            GenericParam::Type { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Generics {
+    pub(crate) params: Vec<GenericParam>,
+    /// The predicates of the `where` clause, without the `where` keyword.
+    pub(crate) where_predicates: TokenStream,
+}
+
+impl Generics {
+    /// `<params>` for an `impl` block, with `extra` parameters appended.
+    pub(crate) fn impl_generics(&self, extra: &[TokenStream]) -> TokenStream {
+        let mut params: Vec<TokenStream> =
+            self.params.iter().map(GenericParam::declaration).collect();
+        params.extend(extra.iter().cloned());
+        angle_list(params)
+    }
+
+    /// A generic list with an extra lifetime and optionally an extra type parameter, mapping the existing parameters with `map`.
+    /// Lifetime parameters have to precede the others, so `lifetime` is placed first and `ty` after the existing lifetimes.
+    pub(crate) fn extended(
+        &self,
+        lifetime: TokenStream,
+        ty: Option<TokenStream>,
+        map: impl Fn(&GenericParam) -> TokenStream,
+    ) -> TokenStream {
+        let mut params = vec![lifetime];
+        params.extend(
+            self.params
+                .iter()
+                .filter(|param| param.is_lifetime())
+                .map(&map),
+        );
+        params.extend(ty);
+        params.extend(
+            self.params
+                .iter()
+                .filter(|param| !param.is_lifetime())
+                .map(&map),
+        );
+        angle_list(params)
+    }
+
+    /// `<arguments>` for naming the type.
+    pub(crate) fn type_arguments(&self) -> TokenStream {
+        angle_list(self.params.iter().map(GenericParam::argument).collect())
+    }
+
+    /// `where predicates` with `extra` predicates appended, or nothing if there are none.
+    pub(crate) fn where_clause(&self, extra: &[TokenStream]) -> TokenStream {
+        let mut tokens = Tokens::new();
+        if self.where_predicates.is_empty() && extra.is_empty() {
+            return tokens.into();
+        }
+        tokens.code("where").tokens(self.where_predicates.clone());
+        if !self.where_predicates.is_empty() && !ends_with_comma(&self.where_predicates) {
+            tokens.code(",");
+        }
+        for predicate in extra {
+            tokens.tokens(predicate.clone()).code(",");
+        }
+        tokens.into()
+    }
+
+    /// Picks a name for an additional type parameter that does not collide with the existing ones.
+    pub(crate) fn fresh_type_name(&self, preferred: &str) -> String {
+        let mut name = preferred.to_string();
+        while self
+            .params
+            .iter()
+            .filter_map(GenericParam::type_name)
+            .any(|ident| ident.to_string() == name)
+        {
+            name.insert_str(0, "__");
+        }
+        name
+    }
+
+    /// Picks a name for an additional lifetime parameter (including the `'`) that does not collide with the existing ones.
+    pub(crate) fn fresh_lifetime_name(&self, preferred: &str) -> String {
+        let mut name = format!("'{preferred}");
+        while self.params.iter().any(|param| match param {
+            GenericParam::Lifetime { name: existing, .. } => existing.to_string() == name,
+            _ => false,
+        }) {
+            name.insert(1, '_');
+        }
+        name
+    }
+}
+
+fn angle_list(items: Vec<TokenStream>) -> TokenStream {
+    let mut tokens = Tokens::new();
+    if items.is_empty() {
+        return tokens.into();
+    }
+    tokens.code("<");
+    for item in items {
+        tokens.tokens(item).code(",");
+    }
+    tokens.code(">");
+    tokens.into()
+}
+
+fn ends_with_comma(stream: &TokenStream) -> bool {
+    matches!(stream.clone().into_iter().last(), Some(TokenTree::Punct(punct)) if punct.as_char() == ',')
+}
+
+pub(crate) struct Field {
+    pub(crate) attrs: Vec<Attribute>,
+    pub(crate) vis: TokenStream,
+    /// The name of the field, or `None` for tuple fields.
+    pub(crate) name: Option<Ident>,
+    pub(crate) index: usize,
+    pub(crate) ty: TokenStream,
+    pub(crate) span: Span,
+}
+
+impl Field {
+    /// The name of the field in field access expressions and patterns (`foo` or `0`).
+    pub(crate) fn member(&self) -> TokenStream {
+        match &self.name {
+            Some(name) => TokenTree::Ident(name.clone()).into(),
+            None => {
+                let mut literal = proc_macro::Literal::usize_unsuffixed(self.index);
+                literal.set_span(self.span);
+                TokenTree::Literal(literal).into()
+            }
+        }
+    }
+
+    /// The name of the field for use in identifiers (`foo` or `0`).
+    pub(crate) fn name_string(&self) -> String {
+        match &self.name {
+            Some(name) => name.to_string().trim_start_matches("r#").to_string(),
+            None => self.index.to_string(),
+        }
+    }
+}
+
+pub(crate) enum Fields {
+    Named(Vec<Field>),
+    Unnamed(Vec<Field>),
+    Unit,
+}
+
+impl Fields {
+    pub(crate) fn iter(&self) -> core::slice::Iter<'_, Field> {
+        match self {
+            Fields::Named(fields) | Fields::Unnamed(fields) => fields.iter(),
+            Fields::Unit => [].iter(),
+        }
+    }
+}
+
+/// A variant of an enum.
+pub(crate) struct Variant {
+    pub(crate) name: Ident,
+    pub(crate) fields: Fields,
+}
+
+/// A struct (or enum) definition, as received by a derive macro.
+pub(crate) struct Item {
+    pub(crate) attrs: Vec<Attribute>,
+    pub(crate) vis: TokenStream,
+    pub(crate) name: Ident,
+    pub(crate) generics: Generics,
+    /// The fields of a struct, or `Fields::Unit` for an enum.
+    pub(crate) fields: Fields,
+    /// The variants of an enum, or `None` for a struct.
+    pub(crate) variants: Option<Vec<Variant>>,
+}
+
+impl Item {
+    pub(crate) fn parse(input: TokenStream) -> Result<Item> {
+        Item::parse_inner(input, false)
+    }
+
+    /// Like `parse`, but enums are accepted as well.
+    pub(crate) fn parse_with_enums(input: TokenStream) -> Result<Item> {
+        Item::parse_inner(input, true)
+    }
+
+    fn parse_inner(input: TokenStream, enums: bool) -> Result<Item> {
+        let mut cursor = Cursor::new(input);
+        let attrs = cursor.attributes();
+        let vis = cursor.visibility();
+        let is_enum = enums && cursor.keyword("enum");
+        if !is_enum && !cursor.keyword("struct") {
+            return Err(cursor.error(if enums {
+                "this macro can only be applied to structs and enums"
+            } else {
+                "this macro can only be applied to structs"
+            }));
+        }
+        let name = cursor
+            .ident()
+            .ok_or_else(|| cursor.error("expected an identifier"))?;
+        let mut generics = Generics {
+            params: cursor.generic_params()?,
+            where_predicates: cursor.where_predicates(),
+        };
+
+        if is_enum {
+            let body = cursor
+                .group(Delimiter::Brace)
+                .ok_or_else(|| cursor.error("expected the variants of the enum"))?;
+            return Ok(Item {
+                attrs,
+                vis,
+                name,
+                generics,
+                fields: Fields::Unit,
+                variants: Some(parse_variants(body.stream())?),
+            });
+        }
+
+        let fields = match cursor.next() {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+                Fields::Named(parse_fields(group.stream(), true)?)
+            }
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                generics.where_predicates = cursor.where_predicates();
+                Fields::Unnamed(parse_fields(group.stream(), false)?)
+            }
+            _ => Fields::Unit,
+        };
+
+        Ok(Item {
+            attrs,
+            vis,
+            name,
+            generics,
+            fields,
+            variants: None,
+        })
+    }
+
+    /// The type of the item with its generic arguments, `Name<'a, T>`.
+    pub(crate) fn self_type(&self) -> TokenStream {
+        let mut tokens = Tokens::new();
+        tokens
+            .ident(&self.name)
+            .tokens(self.generics.type_arguments());
+        tokens.into()
+    }
+
+    /// The type of the item with the generic parameter `param` (like `'a` or `M`) replaced by `replacement`.
+    pub(crate) fn self_type_replacing(&self, param: &str, replacement: TokenStream) -> TokenStream {
+        let arguments = self
+            .generics
+            .params
+            .iter()
+            .map(|generic| {
+                let argument = generic.argument();
+                if argument.to_string() == param {
+                    replacement.clone()
+                } else {
+                    argument
+                }
+            })
+            .collect();
+        let mut tokens = Tokens::new();
+        tokens.ident(&self.name).tokens(angle_list(arguments));
+        tokens.into()
+    }
+}
+
+/// A parameter of a function. The receiver of a method is a parameter without a type, unless it is written as `self: Type`.
+pub(crate) struct FnArg {
+    pub(crate) pattern: TokenStream,
+    pub(crate) ty: Option<TokenStream>,
+}
+
+impl FnArg {
+    /// The name bound by the parameter, if its pattern is a single identifier (like `x` or `mut x`).
+    pub(crate) fn binding(&self) -> Option<Ident> {
+        let mut cursor = Cursor::new(self.pattern.clone());
+        cursor.keyword("mut");
+        cursor.ident().filter(|_| cursor.is_empty())
+    }
+}
+
+/// A function definition, as received by an attribute macro. In traits, the body may be missing.
+pub(crate) struct Function {
+    pub(crate) attrs: Vec<Attribute>,
+    pub(crate) vis: TokenStream,
+    /// The qualifiers preceding `fn`, like `const` or `unsafe`.
+    pub(crate) qualifiers: TokenStream,
+    pub(crate) name: Ident,
+    pub(crate) generics: Generics,
+    pub(crate) inputs: Vec<FnArg>,
+    /// The return type, if there is one.
+    pub(crate) output: Option<TokenStream>,
+    pub(crate) body: Option<Group>,
+}
+
+impl Function {
+    pub(crate) fn parse(input: TokenStream) -> Result<Function> {
+        let mut cursor = Cursor::new(input);
+        let function = Function::parse_from(&mut cursor)?;
+        if !cursor.is_empty() {
+            return Err(cursor.error("unexpected tokens after the function"));
+        }
+        Ok(function)
+    }
+
+    /// Parses a function from the cursor, leaving it after the body (or the `;`).
+    pub(crate) fn parse_from(cursor: &mut Cursor) -> Result<Function> {
+        let attrs = cursor.attributes();
+        let vis = cursor.visibility();
+        let mut qualifiers = Vec::new();
+        while !cursor.is_ident("fn") {
+            match cursor.next() {
+                Some(token @ (TokenTree::Ident(_) | TokenTree::Literal(_))) => {
+                    qualifiers.push(token)
+                }
+                _ => return Err(cursor.error("this macro can only be applied to functions")),
+            }
+        }
+        cursor.keyword("fn");
+        let name = cursor
+            .ident()
+            .ok_or_else(|| cursor.error("expected an identifier"))?;
+        let params = cursor.generic_params()?;
+        let inputs = cursor
+            .group(Delimiter::Parenthesis)
+            .ok_or_else(|| cursor.error("expected the parameters of the function"))?;
+        let inputs = parse_inputs(inputs.stream());
+        let output = cursor.return_type();
+        let generics = Generics {
+            params,
+            where_predicates: cursor.where_predicates(),
+        };
+        let body = cursor.group(Delimiter::Brace);
+        if body.is_none() && !cursor.punct(';') {
+            return Err(cursor.error("expected the body of the function"));
+        }
+        Ok(Function {
+            attrs,
+            vis,
+            qualifiers: qualifiers.into_iter().collect(),
+            name,
+            generics,
+            inputs,
+            output,
+            body,
+        })
+    }
+}
+
+/// Parses the parameters of a function, without the parentheses.
+pub(crate) fn parse_inputs(stream: TokenStream) -> Vec<FnArg> {
+    split_commas(stream, true)
+        .into_iter()
+        .map(|tokens| {
+            let mut cursor = Cursor::new(tokens);
+            cursor.attributes();
+            let mut pattern = Vec::new();
+            // The pattern ends at the first `:` that is not part of a path separator (`::`).
+            while !cursor.is_empty() && !cursor.is_punct(':') {
+                pattern.extend(cursor.next());
+                if cursor.is_joint_colon() {
+                    pattern.extend([cursor.next(), cursor.next()].into_iter().flatten());
+                }
+            }
+            let ty = cursor.punct(':').then(|| cursor.rest());
+            FnArg {
+                pattern: pattern.into_iter().collect(),
+                ty,
+            }
+        })
+        .collect()
+}
+
+fn parse_variants(stream: TokenStream) -> Result<Vec<Variant>> {
+    split_commas(stream, false)
+        .into_iter()
+        .map(|tokens| {
+            let mut cursor = Cursor::new(tokens);
+            cursor.attributes();
+            let name = cursor
+                .ident()
+                .ok_or_else(|| cursor.error("expected a variant name"))?;
+            let fields = if let Some(group) = cursor.group(Delimiter::Brace) {
+                Fields::Named(parse_fields(group.stream(), true)?)
+            } else if let Some(group) = cursor.group(Delimiter::Parenthesis) {
+                Fields::Unnamed(parse_fields(group.stream(), false)?)
+            } else {
+                Fields::Unit
+            };
+            // The rest is the discriminant, if any.
+            Ok(Variant { name, fields })
+        })
+        .collect()
+}
+
+fn parse_fields(stream: TokenStream, named: bool) -> Result<Vec<Field>> {
+    split_commas(stream, true)
+        .into_iter()
+        .enumerate()
+        .map(|(index, tokens)| {
+            let mut cursor = Cursor::new(tokens);
+            let attrs = cursor.attributes();
+            let vis = cursor.visibility();
+            let span = cursor.span();
+            let name = if named {
+                let name = cursor
+                    .ident()
+                    .ok_or_else(|| cursor.error("expected a field name"))?;
+                if !cursor.punct(':') {
+                    return Err(cursor.error("expected `:`"));
+                }
+                Some(name)
+            } else {
+                None
+            };
+            Ok(Field {
+                attrs,
+                vis,
+                name,
+                index,
+                ty: cursor.rest(),
+                span,
+            })
+        })
+        .collect()
+}
+
+/// Splits a token stream at the commas that are not nested in groups (or angle brackets, if `angle_brackets` is set).
+/// Empty segments (such as after a trailing comma) are dropped.
+pub(crate) fn split_commas(stream: TokenStream, angle_brackets: bool) -> Vec<TokenStream> {
+    let mut segments = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0usize;
+    let mut previous_dash = false;
+    for token in stream {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                ',' if depth == 0 => {
+                    segments.push(current.drain(..).collect::<TokenStream>());
+                    previous_dash = false;
+                    continue;
+                }
+                '<' if angle_brackets => depth += 1,
+                // `->` in `fn() -> T` is not a closing angle bracket.
+                '>' if angle_brackets && !previous_dash => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            previous_dash = punct.as_char() == '-';
+        } else {
+            previous_dash = false;
+        }
+        current.push(token);
+    }
+    segments.push(current.into_iter().collect());
+    segments.retain(|segment| !segment.is_empty());
+    segments
+}
+
+/// A cursor over the top-level tokens of a token stream.
+#[derive(Clone)]
+pub(crate) struct Cursor {
+    tokens: Vec<TokenTree>,
+    position: usize,
+}
+
+impl Cursor {
+    pub(crate) fn new(stream: TokenStream) -> Self {
+        Cursor {
+            tokens: stream.into_iter().collect(),
+            position: 0,
+        }
+    }
+
+    pub(crate) fn peek(&self) -> Option<&TokenTree> {
+        self.tokens.get(self.position)
+    }
+
+    pub(crate) fn peek_nth(&self, n: usize) -> Option<&TokenTree> {
+        self.tokens.get(self.position + n)
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub(crate) fn next(&mut self) -> Option<TokenTree> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    /// The span of the next token, or of the last one if there are no more tokens.
+    pub(crate) fn span(&self) -> Span {
+        self.peek()
+            .or(self.tokens.last())
+            .map_or_else(Span::call_site, TokenTree::span)
+    }
+
+    pub(crate) fn error(&self, message: &str) -> Error {
+        Error::new(self.span(), message)
+    }
+
+    pub(crate) fn rest(&mut self) -> TokenStream {
+        let rest = self.tokens[self.position..].iter().cloned().collect();
+        self.position = self.tokens.len();
+        rest
+    }
+
+    pub(crate) fn is_punct(&self, c: char) -> bool {
+        matches!(self.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == c)
+    }
+
+    /// Checks whether the next token is a `:` that is the first half of a `::`.
+    fn is_joint_colon(&self) -> bool {
+        matches!(self.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == ':' && punct.spacing() == Spacing::Joint)
+    }
+
+    /// Checks whether the next item is a function.
+    pub(crate) fn is_function(&self) -> bool {
+        let mut lookahead = self.clone();
+        lookahead.attributes();
+        lookahead.visibility();
+        loop {
+            if lookahead.is_ident("fn") {
+                return true;
+            }
+            let qualifier = ["default", "const", "async", "unsafe", "extern"]
+                .iter()
+                .any(|qualifier| lookahead.is_ident(qualifier))
+                || matches!(lookahead.peek(), Some(TokenTree::Literal(_)));
+            if !qualifier {
+                return false;
+            }
+            lookahead.next();
+        }
+    }
+
+    /// The tokens consumed since the cursor was at `start`, which is a previous value of `position`.
+    pub(crate) fn consumed_since(&self, start: usize) -> TokenStream {
+        self.tokens[start..self.position].iter().cloned().collect()
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    pub(crate) fn is_group(&self, delimiter: Delimiter) -> bool {
+        matches!(self.peek(), Some(TokenTree::Group(group)) if group.delimiter() == delimiter)
+    }
+
+    pub(crate) fn is_ident(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(TokenTree::Ident(ident)) if ident.to_string() == name)
+    }
+
+    /// Consumes the punctuation `c` if it is the next token.
+    pub(crate) fn punct(&mut self, c: char) -> bool {
+        let found = self.is_punct(c);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// Consumes the keyword `name` if it is the next token.
+    pub(crate) fn keyword(&mut self, name: &str) -> bool {
+        let found = self.is_ident(name);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    pub(crate) fn ident(&mut self) -> Option<Ident> {
+        match self.peek() {
+            Some(TokenTree::Ident(ident)) => {
+                let ident = ident.clone();
+                self.position += 1;
+                Some(ident)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn group(&mut self, delimiter: Delimiter) -> Option<Group> {
+        match self.peek() {
+            Some(TokenTree::Group(group)) if group.delimiter() == delimiter => {
+                let group = group.clone();
+                self.position += 1;
+                Some(group)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn attributes(&mut self) -> Vec<Attribute> {
+        let mut attrs = Vec::new();
+        while self.is_punct('#') {
+            let Some(TokenTree::Group(group)) = self.peek_nth(1).cloned() else {
+                break;
+            };
+            let tokens = self.tokens[self.position..self.position + 2]
+                .iter()
+                .cloned()
+                .collect();
+            self.position += 2;
+            let mut inner = Cursor::new(group.stream());
+            let mut name = inner.ident();
+            if inner.is_punct(':') {
+                // A path with multiple segments, like `#[rustfmt::skip]`.
+                name = None;
+            }
+            attrs.push(Attribute {
+                name,
+                args: inner.group(Delimiter::Parenthesis),
+                tokens,
+            });
+        }
+        attrs
+    }
+
+    pub(crate) fn visibility(&mut self) -> TokenStream {
+        let mut vis = Vec::new();
+        if let Some(TokenTree::Ident(ident)) = self.peek() {
+            if ident.to_string() == "pub" {
+                vis.extend(self.next());
+                if let Some(TokenTree::Group(group)) = self.peek() {
+                    let restricted = group.delimiter() == Delimiter::Parenthesis
+                        && matches!(
+                            group.stream().into_iter().next(),
+                            Some(TokenTree::Ident(ident)) if matches!(ident.to_string().as_str(), "crate" | "self" | "super" | "in")
+                        );
+                    if restricted {
+                        vis.extend(self.next());
+                    }
+                }
+            }
+        }
+        vis.into_iter().collect()
+    }
+
+    /// Collects the tokens of an angle-bracketed list, without the brackets, and leaves the cursor after the closing bracket.
+    pub(crate) fn angle_brackets(&mut self) -> Option<TokenStream> {
+        if !self.punct('<') {
+            return None;
+        }
+        let mut depth = 1usize;
+        let mut tokens = Vec::new();
+        let mut previous_dash = false;
+        while let Some(token) = self.next() {
+            if let TokenTree::Punct(punct) = &token {
+                match punct.as_char() {
+                    '<' => depth += 1,
+                    '>' if !previous_dash => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                previous_dash = punct.as_char() == '-';
+            } else {
+                previous_dash = false;
+            }
+            tokens.push(token);
+        }
+        Some(tokens.into_iter().collect())
+    }
+
+    pub(crate) fn generic_params(&mut self) -> Result<Vec<GenericParam>> {
+        let Some(stream) = self.angle_brackets() else {
+            return Ok(Vec::new());
+        };
+        split_commas(stream, true)
+            .into_iter()
+            .map(|tokens| {
+                let mut cursor = Cursor::new(tokens);
+                cursor.attributes();
+                if cursor.is_punct('\'') {
+                    let name: TokenStream = [cursor.next(), cursor.next()]
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                    let bounds = if cursor.punct(':') {
+                        cursor.rest()
+                    } else {
+                        TokenStream::new()
+                    };
+                    Ok(GenericParam::Lifetime { name, bounds })
+                } else if cursor.keyword("const") {
+                    let name = cursor
+                        .ident()
+                        .ok_or_else(|| cursor.error("expected an identifier"))?;
+                    if !cursor.punct(':') {
+                        return Err(cursor.error("expected `:`"));
+                    }
+                    Ok(GenericParam::Const {
+                        name,
+                        ty: cursor.until_default(),
+                    })
+                } else {
+                    let name = cursor
+                        .ident()
+                        .ok_or_else(|| cursor.error("expected a generic parameter"))?;
+                    let bounds = if cursor.punct(':') {
+                        cursor.until_default()
+                    } else {
+                        TokenStream::new()
+                    };
+                    Ok(GenericParam::Type { name, bounds })
+                }
+            })
+            .collect()
+    }
+
+    /// Collects tokens until a default value (`= ...`) that is not nested in angle brackets, or the end.
+    fn until_default(&mut self) -> TokenStream {
+        let mut tokens = Vec::new();
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            if let TokenTree::Punct(punct) = token {
+                match punct.as_char() {
+                    '=' if depth == 0 => break,
+                    '<' => depth += 1,
+                    '>' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            tokens.extend(self.next());
+        }
+        self.position = self.tokens.len();
+        tokens.into_iter().collect()
+    }
+
+    /// Parses a return type (`-> T`) up to a `where` clause, a brace-delimited group or a `;`, if there is one.
+    pub(crate) fn return_type(&mut self) -> Option<TokenStream> {
+        if !(self.is_punct('-')
+            && matches!(self.peek_nth(1), Some(TokenTree::Punct(punct)) if punct.as_char() == '>'))
+        {
+            return None;
+        }
+        self.position += 2;
+        let mut output = Vec::new();
+        while let Some(token) = self.peek() {
+            match token {
+                TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => break,
+                TokenTree::Punct(punct) if punct.as_char() == ';' => break,
+                TokenTree::Ident(ident) if ident.to_string() == "where" => break,
+                _ => output.extend(self.next()),
+            }
+        }
+        Some(output.into_iter().collect())
+    }
+
+    /// Parses a `where` clause up to a brace-delimited group or a `;`, returning its predicates.
+    pub(crate) fn where_predicates(&mut self) -> TokenStream {
+        if !self.keyword("where") {
+            return TokenStream::new();
+        }
+        let mut tokens = Vec::new();
+        while let Some(token) = self.peek() {
+            match token {
+                TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => break,
+                TokenTree::Punct(punct) if punct.as_char() == ';' => break,
+                _ => tokens.extend(self.next()),
+            }
+        }
+        tokens.into_iter().collect()
+    }
+}
+
+/// Collects the flags of the helper attributes called `name`, like `skip` in `#[gen_fields(skip)]`.
+pub(crate) fn helper_flags(attrs: &[Attribute], name: &str) -> Result<Vec<Ident>> {
+    let mut flags = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.is(name)) {
+        let Some(args) = &attr.args else {
+            return Err(Error::new(
+                attr.name.as_ref().map_or_else(Span::call_site, Ident::span),
+                format!("expected arguments, like `#[{name}(...)]`"),
+            ));
+        };
+        for flag in split_commas(args.stream(), false) {
+            let mut cursor = Cursor::new(flag);
+            match cursor.ident() {
+                Some(ident) if cursor.is_empty() => flags.push(ident),
+                _ => {
+                    return Err(Error::new(
+                        args.span(),
+                        format!("unexpected argument in `#[{name}]`"),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(flags)
+}
+
+/// Checks whether the generic parameter `param` (like `'a` or `M`) is mentioned in `tokens`.
+pub(crate) fn mentions(tokens: TokenStream, param: &str) -> bool {
+    let (name, lifetime) = match param.strip_prefix('\'') {
+        Some(name) => (name, true),
+        None => (param, false),
+    };
+    let mut previous_quote = false;
+    for token in tokens {
+        match &token {
+            TokenTree::Group(group) if mentions(group.stream(), param) => return true,
+            TokenTree::Ident(ident) if previous_quote == lifetime && ident.to_string() == name => {
+                return true
+            }
+            _ => {}
+        }
+        previous_quote = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '\'');
+    }
+    false
+}
+
+/// Replaces the generic parameter `param` (like `'a` or `M`) with `replacement` everywhere in `tokens`.
+pub(crate) fn replace_ident(
+    tokens: TokenStream,
+    param: &str,
+    replacement: &TokenStream,
+) -> TokenStream {
+    let (name, lifetime) = match param.strip_prefix('\'') {
+        Some(name) => (name, true),
+        None => (param, false),
+    };
+    let mut output = Vec::new();
+    let mut previous_quote = false;
+    for token in tokens {
+        let quote = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '\'');
+        match token {
+            TokenTree::Group(group) => {
+                let mut new = Group::new(
+                    group.delimiter(),
+                    replace_ident(group.stream(), param, replacement),
+                );
+                new.set_span(group.span());
+                output.push(TokenTree::Group(new));
+            }
+            TokenTree::Ident(ident) if previous_quote == lifetime && ident.to_string() == name => {
+                if lifetime {
+                    // The `'` of the lifetime is part of the replacement.
+                    output.pop();
+                }
+                output.extend(replacement.clone());
+            }
+            token => output.push(token),
+        }
+        previous_quote = quote;
+    }
+    output.into_iter().collect()
+}
+
+/// Splits a type of the form `GenRef<'a, M, T>` (with any path prefix) into its lifetime and target type, if its mutability argument is `m`.
+pub(crate) fn genref_parts(ty: &TokenStream, m: &str) -> Option<(TokenStream, TokenStream)> {
+    let mut arguments = generic_arguments(ty, "GenRef")?.into_iter();
+    let (Some(lifetime), Some(mutability), Some(target), None) = (
+        arguments.next(),
+        arguments.next(),
+        arguments.next(),
+        arguments.next(),
+    ) else {
+        return None;
+    };
+    (mutability.to_string() == m).then_some((lifetime, target))
+}
+
+/// Splits a type of the form `Name<A, B>` (with any path prefix) into its generic arguments.
+pub(crate) fn generic_arguments(ty: &TokenStream, name: &str) -> Option<Vec<TokenStream>> {
+    let mut cursor = Cursor::new(ty.clone());
+    if cursor.punct(':') && !cursor.punct(':') {
+        return None;
+    }
+    loop {
+        let segment = cursor.ident()?;
+        if segment.to_string() == name && cursor.is_punct('<') {
+            break;
+        }
+        if !(cursor.punct(':') && cursor.punct(':')) {
+            return None;
+        }
+    }
+    let arguments = split_commas(cursor.angle_brackets()?, true);
+    cursor.is_empty().then_some(arguments)
+}
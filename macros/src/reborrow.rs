@@ -0,0 +1,78 @@
+use proc_macro::{Delimiter, TokenStream};
+
+use crate::parse::{mentions, Error, Fields, GenericParam, Item, Result};
+use crate::tokens::{code, Tokens};
+
+pub(crate) fn derive(input: TokenStream) -> Result<TokenStream> {
+    let item = Item::parse(input)?;
+    let Some(lifetime) = item.generics.params.iter().find_map(|param| match param {
+        GenericParam::Lifetime { name, .. } => Some(name.to_string()),
+        _ => None,
+    }) else {
+        return Err(Error::new(
+            item.name.span(),
+            "`Reborrow` can only be derived for structs with a lifetime parameter",
+        ));
+    };
+    let reborrowed_lifetime = item.generics.fresh_lifetime_name("r");
+
+    let reborrowed_type = item.self_type_replacing(&lifetime, code(&reborrowed_lifetime));
+
+    // Fields whose type mentions the lifetime are reborrowed, the others are cloned.
+    let field_value = |ty: &TokenStream, member: TokenStream| {
+        let mut tokens = Tokens::new();
+        if mentions(ty.clone(), &lifetime) {
+            tokens.code("::generic_mutability::Reborrow::reborrow");
+        } else {
+            tokens.code("::core::clone::Clone::clone");
+        }
+        tokens.group(Delimiter::Parenthesis, |tokens| {
+            tokens.code("&mut self.").tokens(member);
+        });
+        TokenStream::from(tokens)
+    };
+    let mut body = Tokens::new();
+    body.ident(&item.name);
+    match &item.fields {
+        Fields::Named(fields) => {
+            body.group(Delimiter::Brace, |tokens| {
+                for field in fields {
+                    tokens
+                        .tokens(field.member())
+                        .code(":")
+                        .tokens(field_value(&field.ty, field.member()))
+                        .code(",");
+                }
+            });
+        }
+        Fields::Unnamed(fields) => {
+            body.group(Delimiter::Parenthesis, |tokens| {
+                for field in fields {
+                    tokens
+                        .tokens(field_value(&field.ty, field.member()))
+                        .code(",");
+                }
+            });
+        }
+        Fields::Unit => {}
+    }
+
+    let mut output = Tokens::new();
+    output
+        .code("#[automatically_derived] impl")
+        .tokens(item.generics.impl_generics(&[]))
+        .code("::generic_mutability::Reborrow for")
+        .tokens(item.self_type())
+        .tokens(item.generics.where_clause(&[]))
+        .group(Delimiter::Brace, |tokens| {
+            tokens
+                .code(&format!("type Reborrowed<{reborrowed_lifetime}> ="))
+                .tokens(reborrowed_type)
+                .code(&format!("where Self: {reborrowed_lifetime};"))
+                .code("#[inline] fn reborrow(&mut self) -> Self::Reborrowed<'_>")
+                .group(Delimiter::Brace, |tokens| {
+                    tokens.tokens(body);
+                });
+        });
+    Ok(output.into())
+}
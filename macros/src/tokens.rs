@@ -0,0 +1,91 @@
+//! A small builder for token streams, used instead of a quasi-quoting library.
+//!
+//! Fixed code is written as strings and parsed, while tokens taken from the input are appended as they are, so that they keep their spans.
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Span, TokenStream, TokenTree};
+
+#[derive(Default)]
+pub(crate) struct Tokens {
+    stream: TokenStream,
+}
+
+impl Tokens {
+    pub(crate) fn new() -> Self {
+        Tokens::default()
+    }
+
+    /// Appends fixed code. It must only contain balanced delimiters.
+    pub(crate) fn code(&mut self, fixed: &str) -> &mut Self {
+        self.stream.extend(code(fixed));
+        self
+    }
+
+    /// Appends tokens, usually taken from the input.
+    pub(crate) fn tokens(&mut self, tokens: impl Into<TokenStream>) -> &mut Self {
+        self.stream.extend(tokens.into());
+        self
+    }
+
+    pub(crate) fn ident(&mut self, ident: &Ident) -> &mut Self {
+        self.stream.extend([TokenTree::Ident(ident.clone())]);
+        self
+    }
+
+    /// Appends a new identifier, with the span of `span`.
+    pub(crate) fn new_ident(&mut self, name: &str, span: Span) -> &mut Self {
+        self.ident(&Ident::new(name, span))
+    }
+
+    pub(crate) fn string(&mut self, value: &str) -> &mut Self {
+        self.stream
+            .extend([TokenTree::Literal(Literal::string(value))]);
+        self
+    }
+
+    /// Appends a group, filling its contents with `f`.
+    pub(crate) fn group(&mut self, delimiter: Delimiter, f: impl FnOnce(&mut Tokens)) -> &mut Self {
+        let mut inner = Tokens::new();
+        f(&mut inner);
+        self.stream
+            .extend([TokenTree::Group(Group::new(delimiter, inner.stream))]);
+        self
+    }
+
+    /// Appends a `#[doc = "..."]` attribute.
+    pub(crate) fn doc(&mut self, doc: &str) -> &mut Self {
+        self.code("#").group(Delimiter::Bracket, |tokens| {
+            tokens.code("doc =").string(doc);
+        })
+    }
+
+    /// Sets the span of every token (including the ones in nested groups) to `span`.
+    pub(crate) fn with_span(self, span: Span) -> TokenStream {
+        fn respan(stream: TokenStream, span: Span) -> TokenStream {
+            stream
+                .into_iter()
+                .map(|mut token| {
+                    if let TokenTree::Group(group) = &token {
+                        let mut new = Group::new(group.delimiter(), respan(group.stream(), span));
+                        new.set_span(span);
+                        token = TokenTree::Group(new);
+                    } else {
+                        token.set_span(span);
+                    }
+                    token
+                })
+                .collect()
+        }
+        respan(self.stream, span)
+    }
+}
+
+/// Parses fixed code. It must only contain balanced delimiters.
+pub(crate) fn code(code: &str) -> TokenStream {
+    code.parse().expect("generated code failed to parse")
+}
+
+impl From<Tokens> for TokenStream {
+    fn from(tokens: Tokens) -> Self {
+        tokens.stream
+    }
+}
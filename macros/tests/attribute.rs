@@ -0,0 +1,355 @@
+#![cfg(test)]
+
+use generic_mutability::*;
+
+struct Stack<T> {
+    items: Vec<T>,
+}
+
+impl<T> Stack<T> {
+    /// Returns the top item of the stack.
+    #[gen_mut_pair(top, top_mut)]
+    fn gen_top<M: Mutability>(this: GenRef<'_, M, Self>) -> Option<GenRef<'_, M, T>> {
+        GenRef::try_map(
+            this,
+            |stack| stack.items.last(),
+            |stack| stack.items.last_mut(),
+        )
+    }
+
+    #[gen_mut_pair(nth, nth_mut)]
+    fn gen_nth<'a, M: Mutability, I>(this: GenRef<'a, M, Self>, index: I) -> GenRef<'a, M, T>
+    where
+        I: Into<usize>,
+    {
+        let index = index.into();
+        GenRef::map(
+            this,
+            |stack| &stack.items[index],
+            |stack| &mut stack.items[index],
+        )
+    }
+
+    #[gen_mut_pair(split, split_mut)]
+    fn gen_split<M: Mutability>(
+        this: GenRef<'_, M, Self>,
+        (skip, _): (usize, ()),
+    ) -> (GenRef<'_, M, [T]>, usize) {
+        let slice = GenRef::map(
+            this,
+            |stack| &stack.items[skip..],
+            |stack| &mut stack.items[skip..],
+        );
+        let len = slice.len();
+        (slice, len)
+    }
+}
+
+#[gen_mut_pair(first, first_mut, free)]
+fn gen_first<M: Mutability, T>(slice: GenRef<'_, M, [T]>) -> GenRef<'_, M, T> {
+    GenRef::map(slice, |slice| &slice[0], |slice| &mut slice[0])
+}
+
+struct Util;
+
+impl Util {
+    #[gen_mut_pair(first, first_mut)]
+    fn gen_first<M: Mutability>(bytes: GenRef<'_, M, [u8]>) -> GenRef<'_, M, u8> {
+        GenRef::map(bytes, |bytes| &bytes[0], |bytes| &mut bytes[0])
+    }
+}
+
+impl<T> Stack<T> {
+    #[gen_mut_pair(last, last_mut)]
+    fn gen_last<M: Mutability>(items: GenRef<'_, M, [T]>) -> GenRef<'_, M, T> {
+        GenRef::map(
+            items,
+            |items| &items[items.len() - 1],
+            |items| {
+                let last = items.len() - 1;
+                &mut items[last]
+            },
+        )
+    }
+}
+
+#[test]
+fn gen_mut_pair_wrappers() {
+    let mut stack = Stack {
+        items: vec![1, 2, 3],
+    };
+    assert_eq!(stack.top(), Some(&3));
+    *stack.top_mut().unwrap() = 30;
+    *stack.nth_mut(0u8) += 10;
+    assert_eq!(*stack.nth(1u16), 2);
+
+    let (rest, len): (&mut [i32], usize) = {
+        let (rest, len) = stack.split_mut((1, ()));
+        (GenRef::into_mut(rest), len)
+    };
+    rest[0] = 20;
+    assert_eq!(len, 2);
+    let (rest, _) = stack.split((2, ()));
+    assert_eq!(*rest, [30]);
+
+    *first_mut(&mut stack.items[..]) += 1;
+    assert_eq!(first(&stack.items[..]), &12);
+    assert_eq!(stack.items, [12, 20, 30]);
+}
+
+#[test]
+fn gen_mut_pair_associated_function() {
+    let mut bytes = [1, 2, 3];
+    *Util::first_mut(&mut bytes) += 1;
+    assert_eq!(Util::first(&bytes), &2);
+
+    *Stack::last_mut(&mut bytes) += 1;
+    assert_eq!(Stack::<u8>::last(&bytes), &4);
+}
+
+#[allow(clippy::type_complexity)]
+#[generic_mutability]
+fn gen_split_last<T>(slice: &gen<[T]>) -> Option<(&gen<T>, &gen<[T]>)> {
+    let (last, rest) =
+        switch_shared_mut![<[T]>::split_last, <[T]>::split_last_mut](from_gen!(slice))?;
+    Some((into_gen!(last), into_gen!(rest)))
+}
+
+#[generic_mutability(N)]
+fn gen_second<'a, N: Mutability, T>(slice: &'a gen<[T]>) -> Option<&'a gen<T>>
+where
+    T: Copy,
+{
+    gen_split_last(slice).and_then(|(_, rest)| gen_split_last(rest).map(|(second, _)| second))
+}
+
+trait Named {
+    const NAME: &'static str;
+    type Key;
+
+    fn key(&self) -> Self::Key;
+}
+
+struct Entry {
+    name: &'static str,
+    hits: u32,
+}
+
+#[generic_mutability]
+impl Named for Entry {
+    const NAME: &'static str = "entry";
+    type Key = &'static str;
+
+    fn key(&self) -> Self::Key {
+        self.name
+    }
+}
+
+#[generic_mutability]
+impl Entry {
+    const LIMIT: u32 = 2;
+
+    fn gen_hits(this: &gen<Self>) -> &gen<u32> {
+        GenRef::map(this, |entry| &entry.hits, |entry| &mut entry.hits)
+    }
+
+    fn hit(&mut self) -> bool {
+        let mut hits = Entry::gen_hits(GenRef::from(self));
+        *hits += 1;
+        *hits <= Self::LIMIT
+    }
+}
+
+#[test]
+fn generic_mutability_signatures() {
+    let mut values = [1, 2, 3];
+    let (last, mut rest) = gen_split_last(GenRef::from(&mut values[..])).unwrap();
+    rest[0] = 10;
+    assert_eq!(*last, 3);
+    *gen_second(GenRef::from(&mut values[..])).unwrap() = 20;
+    assert_eq!(*gen_second(GenRef::from(&values[..])).unwrap(), 20);
+    assert_eq!(values, [10, 20, 3]);
+    assert!(gen_second(GenRef::from(&values[..1])).is_none());
+
+    let mut entry = Entry { name: "a", hits: 0 };
+    assert_eq!((Entry::NAME, entry.key()), ("entry", "a"));
+    assert!(entry.hit());
+    assert!(entry.hit());
+    assert!(!entry.hit());
+    assert_eq!(*Entry::gen_hits(GenRef::from(&entry)), 3);
+}
+
+#[gen_trait]
+trait Store {
+    type Item;
+    const CAPACITY: usize;
+
+    /// Returns the item with the given key.
+    fn item<'a, K>(&'a self, key: K) -> &'a Self::Item
+    where
+        K: AsRef<str> + Copy;
+    fn item_mut<'a, K>(&'a mut self, key: K) -> &'a mut Self::Item
+    where
+        K: AsRef<str> + Copy;
+
+    fn first(&self) -> Option<&Self::Item>;
+    fn first_mut(&mut self) -> Option<&mut Self::Item>;
+
+    fn name(&self) -> &str;
+
+    fn is_full(&self) -> bool {
+        self.name().len() >= Self::CAPACITY
+    }
+}
+
+struct Shelf {
+    label: String,
+    slots: Vec<(String, u32)>,
+}
+
+impl Store for Shelf {
+    type Item = u32;
+    const CAPACITY: usize = 4;
+
+    fn gen_item<'a, K, M: Mutability>(this: GenRef<'a, M, Self>, key: K) -> GenRef<'a, M, u32>
+    where
+        K: AsRef<str> + Copy,
+    {
+        let index = this
+            .slots
+            .iter()
+            .position(|(name, _)| name == key.as_ref())
+            .unwrap();
+        GenRef::map(
+            this,
+            |shelf| &shelf.slots[index].1,
+            |shelf| &mut shelf.slots[index].1,
+        )
+    }
+
+    fn gen_first<M: Mutability>(this: GenRef<'_, M, Self>) -> Option<GenRef<'_, M, u32>> {
+        GenRef::try_map(
+            this,
+            |shelf| shelf.slots.first().map(|slot| &slot.1),
+            |shelf| shelf.slots.first_mut().map(|slot| &mut slot.1),
+        )
+    }
+
+    fn name(&self) -> &str {
+        &self.label
+    }
+}
+
+#[test]
+fn gen_trait_pairs() {
+    let mut shelf = Shelf {
+        label: "top".to_string(),
+        slots: vec![("a".to_string(), 1), ("b".to_string(), 2)],
+    };
+    *shelf.item_mut("b") += 10;
+    *shelf.first_mut().unwrap() += 100;
+    assert_eq!((*shelf.item("a"), *shelf.item("b")), (101, 12));
+    assert_eq!(shelf.first(), Some(&101));
+    assert_eq!(*Shelf::gen_item(GenRef::from(&shelf), "b"), 12);
+    assert!(!shelf.is_full());
+    assert_eq!(shelf.name(), "top");
+}
+
+#[gen_trait]
+trait Tree {
+    fn node(&self, key: &str) -> Option<&String>;
+    fn node_mut(&mut self, key: &str) -> Option<&mut String>;
+}
+
+struct Leaves(Vec<(String, String)>);
+
+impl Tree for Leaves {
+    fn gen_node<'s, M: Mutability>(
+        this: GenRef<'s, M, Self>,
+        key: &str,
+    ) -> Option<GenRef<'s, M, String>> {
+        let index = this.0.iter().position(|(name, _)| name == key)?;
+        Some(GenRef::map(
+            this,
+            |leaves| &leaves.0[index].1,
+            |leaves| &mut leaves.0[index].1,
+        ))
+    }
+}
+
+#[test]
+fn gen_trait_reference_parameters() {
+    let mut leaves = Leaves(vec![("a".to_string(), "x".to_string())]);
+    leaves.node_mut("a").unwrap().push('y');
+    assert_eq!(leaves.node("a").map(String::as_str), Some("xy"));
+    assert_eq!(leaves.node("b"), None);
+}
+
+#[gen_trait]
+#[gen_dyn]
+trait Widget: core::fmt::Debug {
+    fn label(&self) -> &String;
+    fn label_mut(&mut self) -> &mut String;
+
+    fn gen_size<'w, M: Mutability>(
+        this: GenRef<'w, M, Self>,
+        index: usize,
+    ) -> Option<GenRef<'w, M, u32>>;
+
+    fn grow(&mut self, size: u32);
+
+    fn describe(&self) -> String {
+        format!("{}: {:?}", self.label(), self)
+    }
+}
+
+#[derive(Debug)]
+struct Button {
+    label: String,
+    sizes: Vec<u32>,
+}
+
+impl Widget for Button {
+    fn gen_label<M: Mutability>(this: GenRef<'_, M, Self>) -> GenRef<'_, M, String> {
+        GenRef::map(this, |button| &button.label, |button| &mut button.label)
+    }
+
+    fn gen_size<'w, M: Mutability>(
+        this: GenRef<'w, M, Self>,
+        index: usize,
+    ) -> Option<GenRef<'w, M, u32>> {
+        GenRef::try_map(
+            this,
+            |button| button.sizes.get(index),
+            |button| button.sizes.get_mut(index),
+        )
+    }
+
+    fn grow(&mut self, size: u32) {
+        self.sizes.push(size);
+    }
+}
+
+#[test]
+fn gen_dyn_trait_objects() {
+    let mut widgets: Vec<Box<dyn WidgetDyn>> = vec![Box::new(Button {
+        label: "ok".to_string(),
+        sizes: vec![1, 2],
+    })];
+    let widget = &mut *widgets[0];
+    WidgetDyn::grow(widget, 3);
+    *GenDyn::from(&mut *widget).gen_size(2).unwrap() += 10;
+    GenDyn::from(&mut *widget).gen_label().push('!');
+    Widget::label_mut(widget).push('?');
+    assert_eq!(widget.gen_size_shared(0), Some(&1));
+    assert_eq!(widget.gen_size_mut(3), None);
+
+    let mut widget = GenDyn::from(&*widgets[0]);
+    assert_eq!(*widget.reborrow().gen_label(), "ok!?");
+    assert_eq!(widget.reborrow().gen_size(2).as_deref(), Some(&13));
+    assert_eq!(
+        Widget::describe(&*widget),
+        r#"ok!?: Button { label: "ok!?", sizes: [1, 2, 13] }"#
+    );
+    assert_eq!(Widget::label(&*widget), "ok!?");
+}
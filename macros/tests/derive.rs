@@ -0,0 +1,378 @@
+#![cfg(test)]
+
+use generic_mutability::*;
+
+mod model {
+    use generic_mutability::GenFields;
+
+    #[derive(GenFields)]
+    pub struct Account<'a, T: Clone, const N: usize = 2>
+    where
+        T: Default,
+    {
+        pub owner: &'a str,
+        pub(crate) balances: [T; N],
+        #[gen_fields(skip)]
+        pub audit: Vec<T>,
+        history: Option<Box<Account<'a, T, N>>>,
+    }
+
+    impl<'a, T: Clone + Default, const N: usize> Account<'a, T, N> {
+        pub fn new(owner: &'a str) -> Self {
+            Account {
+                owner,
+                balances: core::array::from_fn(|_| T::default()),
+                audit: Vec::new(),
+                history: None,
+            }
+        }
+
+        pub fn with_history(mut self, history: Self) -> Self {
+            self.history = Some(Box::new(history));
+            self
+        }
+
+        pub fn gen_history_owner<M: generic_mutability::Mutability>(
+            this: generic_mutability::GenRef<'_, M, Self>,
+        ) -> Option<generic_mutability::GenRef<'_, M, &'a str>> {
+            generic_mutability::GenRef::transpose(Self::gen_history(this))
+                .map(|history| Self::gen_owner(generic_mutability::GenRef::map_deref(history)))
+        }
+    }
+
+    #[derive(GenFields)]
+    pub struct Pair<M>(pub M, pub(crate) u8);
+}
+
+use model::{Account, Pair};
+
+use generic_mutability::GenView;
+
+fn gen_first_balance<'a, M: Mutability>(
+    account: GenRef<'a, M, Account<'_, i64, 2>>,
+) -> GenRef<'a, M, i64> {
+    field!(&gen {Account::gen_balances(account)}[0])
+}
+
+#[test]
+fn gen_fields_accessors() {
+    let mut account = Account::<i64>::new("alice").with_history(Account::new("bob"));
+
+    *gen_first_balance(GenRef::from(&mut account)) = 10;
+    assert_eq!(account.balances, [10, 0]);
+    assert!(account.audit.is_empty());
+    assert_eq!(*Account::gen_owner(GenRef::from(&account)), "alice");
+
+    *Account::gen_history_owner(GenRef::from(&mut account)).unwrap() = "carol";
+    assert_eq!(
+        *Account::gen_history_owner(GenRef::from(&account)).unwrap(),
+        "carol"
+    );
+
+    let mut pair = Pair('x', 1);
+    *Pair::gen_1(GenRef::from(&mut pair)) += 1;
+    assert_eq!(*Pair::gen_0(GenRef::from(&pair)), 'x');
+    assert_eq!(pair.1, 2);
+}
+
+#[derive(GenView)]
+struct Cursor<'s, T, const N: usize>
+where
+    T: Copy,
+{
+    buffer: &'s [T; N],
+    position: usize,
+    history: Vec<T>,
+}
+
+#[derive(GenView)]
+struct Span(u32, u32);
+
+fn gen_advance<M: Mutability, T: Copy, const N: usize>(
+    mut view: CursorView<'_, '_, M, T, N>,
+) -> Option<T> {
+    let value = *view.buffer.get(*view.position)?;
+    gen_record(view.reborrow(), value);
+    Some(value)
+}
+
+fn gen_record<M: Mutability, T: Copy, const N: usize>(view: CursorView<'_, '_, M, T, N>, value: T) {
+    if let MutabilityEnum::Mutable(proof) = M::mutability() {
+        *GenRef::gen_into_mut(view.position, proof) += 1;
+        GenRef::gen_into_mut(view.history, proof).push(value);
+    }
+}
+
+#[test]
+fn gen_view_split() {
+    let buffer = [1, 2, 3];
+    let mut cursor = Cursor {
+        buffer: &buffer,
+        position: 0,
+        history: Vec::new(),
+    };
+
+    assert_eq!(
+        gen_advance(Cursor::gen_view(GenRef::from(&cursor))),
+        Some(1)
+    );
+    assert_eq!(cursor.position, 0);
+
+    let mut view = CursorView::from(&mut cursor);
+    assert_eq!(gen_advance(view.reborrow()), Some(1));
+    assert_eq!(gen_advance(view.reborrow()), Some(2));
+    let shared: CursorView<'_, '_, Shared, i32, 3> = view.into();
+    assert_eq!(*shared.history, [1, 2]);
+    assert_eq!(cursor.position, 2);
+
+    let mut span = Span(1, 2);
+    let SpanView(start, mut end) = Span::gen_view(GenRef::from(&mut span));
+    *end += *start;
+    assert_eq!((span.0, span.1), (1, 3));
+}
+
+#[derive(Reborrow)]
+struct Walker<'a, M: Mutability> {
+    totals: GenRef<'a, M, Vec<u32>>,
+    limit: Option<&'a u32>,
+    depth: usize,
+}
+
+#[derive(Reborrow)]
+struct Counter<'a>(&'a mut u32, u32);
+
+fn gen_walk<M: Mutability>(mut walker: Walker<'_, M>, value: u32) -> usize {
+    if walker.limit.is_some_and(|limit| value > *limit) {
+        return 0;
+    }
+    walker.depth += 1;
+    if let MutabilityEnum::Mutable(proof) = M::mutability() {
+        GenRef::gen_into_mut(GenRef::reborrow(&mut walker.totals), proof).push(value);
+    }
+    let left = gen_walk(walker.reborrow(), value * 2);
+    let depth = walker.depth;
+    depth + left + gen_walk(walker, value * 2 + 1)
+}
+
+#[test]
+fn reborrow_derive() {
+    let mut totals = Vec::new();
+    let limit = 5;
+    let walker = Walker {
+        totals: GenRef::from(&mut totals),
+        limit: Some(&limit),
+        depth: 0,
+    };
+    assert_eq!(gen_walk(walker, 1), 11);
+    assert_eq!(totals, [1, 2, 4, 5, 3]);
+
+    let shared = Vec::new();
+    let walker = Walker::<Shared> {
+        totals: GenRef::from(&shared),
+        limit: None,
+        depth: 5,
+    };
+    let mut walker = walker;
+    assert_eq!(walker.reborrow().depth, 5);
+
+    let mut count = 0;
+    let mut counter = Counter(&mut count, 2);
+    for _ in 0..3 {
+        let Counter(count, step) = counter.reborrow();
+        *count += step;
+    }
+    assert_eq!(count, 6);
+}
+
+#[derive(Downgrade)]
+struct Matches<'a, T, M: Mutability> {
+    first: Option<GenRef<'a, M, T>>,
+    all: Vec<&'a T>,
+    bounds: (GenRef<'a, M, T>, GenRef<'a, M, T>),
+}
+
+#[derive(Downgrade)]
+struct Hit<'a, M: Mutability>(GenRef<'a, M, i32>, usize);
+
+fn gen_find<M: Mutability>(values: GenRef<'_, M, [i32]>, target: i32) -> Option<Hit<'_, M>> {
+    let index = values.iter().position(|value| *value == target)?;
+    let value = GenRef::map(values, |values| &values[index], |values| &mut values[index]);
+    Some(Hit(value, index))
+}
+
+#[test]
+fn downgrade_derive() {
+    let mut values = [4, 5, 6];
+    let mut hit = gen_find(GenRef::from(&mut values[..]), 5).unwrap();
+    *hit.0 += 10;
+    let Hit(value, index) = hit.downgrade();
+    let shared: [GenRef<'_, Shared, i32>; 2] = [value, value];
+    assert_eq!((*shared[0], index), (15, 1));
+
+    let (mut low, mut high) = (1, 9);
+    let matches = Matches {
+        first: None,
+        all: vec![&values[0]],
+        bounds: (GenRef::from(&mut low), GenRef::from(&mut high)),
+    };
+    let matches: Matches<'_, i32, Shared> = matches.downgrade();
+    assert!(matches.first.is_none());
+    assert_eq!(
+        (*matches.all[0], *matches.bounds.0, *matches.bounds.1),
+        (4, 1, 9)
+    );
+}
+
+#[derive(Reborrow, Downgrade)]
+struct Stretch<'a, M: Mutability> {
+    span: SpanView<'a, M>,
+    step: u32,
+}
+
+fn gen_stretch<M: Mutability>(stretch: Stretch<'_, M>) {
+    if let MutabilityEnum::Mutable(proof) = M::mutability() {
+        *GenRef::gen_into_mut(stretch.span.1, proof) += stretch.step;
+    }
+}
+
+#[test]
+fn gen_view_nested() {
+    let mut span = Span(1, 2);
+    let mut stretch = Stretch {
+        span: SpanView::from(&mut span),
+        step: 3,
+    };
+    gen_stretch(stretch.reborrow());
+    gen_stretch(stretch.reborrow());
+    let stretch: Stretch<'_, Shared> = stretch.downgrade();
+    assert_eq!((*stretch.span.0, *stretch.span.1), (1, 8));
+}
+
+mod tree {
+    use generic_mutability::GenVisit;
+
+    #[derive(GenVisit)]
+    #[gen_visit(visitor(Item, Attr, Expr))]
+    pub struct Module {
+        pub items: Vec<Item>,
+        #[gen_visit(skip)]
+        pub name: String,
+    }
+
+    #[derive(GenVisit)]
+    pub enum Item {
+        Fn {
+            attrs: Vec<Option<Attr>>,
+            #[gen_visit(skip)]
+            name: String,
+            body: Option<Box<Expr>>,
+        },
+        Mod(Box<Module>),
+        Empty,
+    }
+
+    #[derive(GenVisit)]
+    pub struct Attr(#[gen_visit(skip)] pub String);
+
+    #[derive(GenVisit)]
+    pub enum Expr {
+        Number(#[gen_visit(skip)] u32),
+        Call(Box<self::Expr>, Vec<Expr>),
+    }
+}
+
+use tree::*;
+
+struct Renamer;
+
+impl<M: Mutability> GenVisitor<M> for Renamer {
+    fn visit_attr(&mut self, node: GenRef<'_, M, Attr>) {
+        if let MutabilityEnum::Mutable(proof) = M::mutability() {
+            GenRef::gen_into_mut(node, proof).0.make_ascii_uppercase();
+        }
+    }
+
+    fn visit_expr(&mut self, mut node: GenRef<'_, M, Expr>) {
+        if let MutabilityEnum::Mutable(proof) = M::mutability() {
+            if let Expr::Number(value) = GenRef::gen_into_mut(GenRef::reborrow(&mut node), proof) {
+                *value += 1;
+            }
+        }
+        walk_expr(self, node);
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    names: Vec<String>,
+    attrs: Vec<String>,
+    numbers: Vec<u32>,
+}
+
+impl GenVisitor<Shared> for Collector {
+    fn visit_module(&mut self, node: GenRef<'_, Shared, Module>) {
+        self.names.push(node.name.clone());
+        walk_module(self, node);
+    }
+
+    fn visit_item(&mut self, node: GenRef<'_, Shared, Item>) {
+        if let Item::Fn { name, .. } = &*node {
+            self.names.push(name.clone());
+        }
+        walk_item(self, node);
+    }
+
+    fn visit_attr(&mut self, node: GenRef<'_, Shared, Attr>) {
+        self.attrs.push(node.0.clone());
+        walk_attr(self, node);
+    }
+
+    fn visit_expr(&mut self, node: GenRef<'_, Shared, Expr>) {
+        if let Expr::Number(value) = *node {
+            self.numbers.push(value);
+        }
+        walk_expr(self, node);
+    }
+}
+
+#[test]
+fn gen_visit_derive() {
+    let mut module = Module {
+        name: "root".to_string(),
+        items: vec![
+            Item::Fn {
+                attrs: vec![Some(Attr("inline".to_string())), None],
+                name: "main".to_string(),
+                body: Some(Box::new(Expr::Call(
+                    Box::new(Expr::Number(1)),
+                    vec![Expr::Number(2), Expr::Number(3)],
+                ))),
+            },
+            Item::Mod(Box::new(Module {
+                name: "inner".to_string(),
+                items: vec![
+                    Item::Empty,
+                    Item::Fn {
+                        attrs: vec![Some(Attr("test".to_string()))],
+                        name: "check".to_string(),
+                        body: None,
+                    },
+                ],
+            })),
+        ],
+    };
+
+    let mut collector = Collector::default();
+    collector.visit_module(GenRef::from(&module));
+    assert_eq!(collector.names, ["root", "main", "inner", "check"]);
+    assert_eq!(collector.attrs, ["inline", "test"]);
+    assert_eq!(collector.numbers, [1, 2, 3]);
+
+    Renamer.visit_module(GenRef::from(&mut module));
+    Renamer.visit_module(GenRef::from(&module));
+    let mut collector = Collector::default();
+    walk_module(&mut collector, GenRef::from(&module));
+    assert_eq!(collector.names, ["main", "inner", "check"]);
+    assert_eq!(collector.attrs, ["INLINE", "TEST"]);
+    assert_eq!(collector.numbers, [2, 3, 4]);
+}
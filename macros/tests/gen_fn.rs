@@ -0,0 +1,91 @@
+#![cfg(test)]
+
+use generic_mutability::*;
+
+mod ring {
+    pub struct Ring<T> {
+        pub(crate) items: Vec<T>,
+        pub(crate) head: usize,
+    }
+
+    impl<T> Ring<T> {
+        pub fn peek(&self, offset: usize) -> &T {
+            &self.items[(self.head + offset) % self.items.len()]
+        }
+        pub fn peek_mut(&mut self, offset: usize) -> &mut T {
+            let len = self.items.len();
+            &mut self.items[(self.head + offset) % len]
+        }
+        pub fn find<P: Fn(&T) -> bool>(&self, predicate: P) -> Option<&T> {
+            self.items.iter().find(|item| predicate(item))
+        }
+        pub fn find_mut<P: Fn(&T) -> bool>(&mut self, predicate: P) -> Option<&mut T> {
+            self.items.iter_mut().find(|item| predicate(item))
+        }
+        pub fn rotate(&self) {}
+        pub fn rotate_mut(&mut self) {
+            self.head = (self.head + 1) % self.items.len();
+        }
+    }
+}
+
+use ring::Ring;
+
+gen_fn!(pub fn gen_peek<T> = Ring::<T>::peek / Ring::<T>::peek_mut (offset: usize) -> T);
+gen_fn! {
+    /// Finds the first item matching the predicate.
+    fn gen_find<T, P> = Ring::<T>::find / Ring::<T>::find_mut (predicate: P) -> Option<&T>
+    where
+        P: Fn(&T) -> bool;
+}
+gen_fn!(fn gen_rotate<T> = Ring::<T>::rotate / Ring::<T>::rotate_mut ());
+
+extension_trait! {
+    /// Generically mutable methods of `Ring`.
+    pub(crate) trait GenRingExt<T> for Ring<T> where T: Copy {
+        fn gen_peek = Ring::peek / Ring::peek_mut (offset: usize) -> T;
+        /// Rotates the ring if it is mutable.
+        fn gen_rotate = Ring::rotate / Ring::rotate_mut ();
+        fn gen_find<P: Fn(&T) -> bool> = Ring::find / Ring::find_mut (predicate: P) -> Option<&T>;
+    }
+}
+
+#[test]
+fn gen_fn_dispatch() {
+    let mut ring = Ring {
+        items: vec![1, 2, 3],
+        head: 0,
+    };
+
+    *gen_peek(GenRef::from(&mut ring), 1) = 20;
+    gen_rotate(GenRef::from(&mut ring));
+    gen_rotate(GenRef::from(&ring));
+    assert_eq!(*gen_peek(GenRef::from(&ring), 0), 20);
+    *gen_find(GenRef::from(&mut ring), |item| *item == 3).unwrap() = 30;
+    assert!(gen_find(GenRef::from(&ring), |item| *item == 3).is_none());
+    assert_eq!(ring.items, [1, 20, 30]);
+}
+
+#[test]
+fn extension_trait_methods() {
+    fn gen_bump<M: Mutability>(mut ring: GenRef<'_, M, Ring<u8>>) -> u8 {
+        GenRef::reborrow(&mut ring).gen_rotate();
+        let value = *GenRef::reborrow(&mut ring).gen_peek(0);
+        if let MutabilityEnum::Mutable(proof) = M::mutability() {
+            if let Some(item) = ring.gen_find(|item| *item == value) {
+                *GenRef::gen_into_mut(item, proof) += 1;
+            }
+        }
+        value
+    }
+
+    let mut ring = Ring {
+        items: vec![1, 2, 3],
+        head: 0,
+    };
+    assert_eq!(gen_bump(GenRef::from(&ring)), 1);
+    assert_eq!(gen_bump(GenRef::from(&mut ring)), 2);
+    assert_eq!(gen_bump(GenRef::from(&mut ring)), 3);
+    assert_eq!(ring.items, [1, 4, 3]);
+    assert_eq!(*GenRef::from(&ring).gen_peek(1), 1);
+}
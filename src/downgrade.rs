@@ -0,0 +1,68 @@
+use crate::{GenRef, Mutability, Shared};
+
+/// Types generic over mutability that can be converted into their shared counterpart.
+///
+/// Downgrading maps `Self<M>` to `Self<Shared>`, like `gen_into_shared_downgrading` does for a single `GenRef`.
+/// This is implemented for `GenRef`, and for `Option`s, tuples and arrays of downgradable values.
+///
+/// The implementation for a struct with a mutability parameter can be derived with `#[derive(Downgrade)]` (requires the feature flag `macros`).
+///
+/// ```rust
+/// # use generic_mutability::{Downgrade, GenRef, Shared};
+/// let mut values = [1, 2];
+/// let [first, second] = values.each_mut().map(GenRef::from);
+///
+/// let shared: (GenRef<'_, Shared, i32>, Option<GenRef<'_, Shared, i32>>) = (first, Some(second)).downgrade();
+/// assert_eq!((*shared.0, *shared.1.unwrap()), (1, 2));
+/// ```
+pub trait Downgrade {
+    /// The shared counterpart of the type.
+    type Downgraded;
+
+    /// Converts the value into its shared counterpart.
+    fn downgrade(self) -> Self::Downgraded;
+}
+
+impl<'s, M: Mutability, T: ?Sized> Downgrade for GenRef<'s, M, T> {
+    type Downgraded = GenRef<'s, Shared, T>;
+
+    #[inline]
+    fn downgrade(self) -> Self::Downgraded {
+        GenRef::from_shared(GenRef::gen_into_shared_downgrading(self))
+    }
+}
+
+impl<D: Downgrade> Downgrade for Option<D> {
+    type Downgraded = Option<D::Downgraded>;
+
+    #[inline]
+    fn downgrade(self) -> Self::Downgraded {
+        self.map(D::downgrade)
+    }
+}
+
+impl<D: Downgrade, const N: usize> Downgrade for [D; N] {
+    type Downgraded = [D::Downgraded; N];
+
+    #[inline]
+    fn downgrade(self) -> Self::Downgraded {
+        self.map(D::downgrade)
+    }
+}
+
+macro_rules! impl_downgrade_tuple {
+    ([$($done:ident $done_index:tt)*] $name:ident $index:tt $(, $rest:ident $rest_index:tt)*) => {
+        impl<$($done: Downgrade,)* $name: Downgrade> Downgrade for ($($done,)* $name,) {
+            type Downgraded = ($($done::Downgraded,)* $name::Downgraded,);
+
+            #[inline]
+            fn downgrade(self) -> Self::Downgraded {
+                ($(self.$done_index.downgrade(),)* self.$index.downgrade(),)
+            }
+        }
+        impl_downgrade_tuple!([$($done $done_index)* $name $index] $($rest $rest_index),*);
+    };
+    ([$($done:ident $done_index:tt)*]) => {};
+}
+
+impl_downgrade_tuple!([] A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
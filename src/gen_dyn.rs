@@ -0,0 +1,86 @@
+use core::ops::Deref;
+
+use crate::{Downgrade, GenRef, Mutability, Mutable, Reborrow, Shared};
+
+/// A generically mutable reference to a trait object.
+///
+/// Traits with methods generic over mutability (like `fn gen_x<M: Mutability>(this: GenRef<'_, M, Self>)`) are not dyn-compatible.
+/// With the feature flag `macros`, `#[gen_dyn]` generates a dyn-compatible companion trait `TraitDyn` for such a trait, with a shared and a mutable entry for each generic method.
+/// It also generates an extension trait `TraitGenDyn` for `GenDyn<'a, M, dyn TraitDyn>`, whose generic methods dispatch to the right entry depending on `M`.
+///
+/// `GenDyn` is a thin wrapper around a `GenRef`, and it dereferences to the trait object.
+#[repr(transparent)]
+pub struct GenDyn<'a, M: Mutability, T: ?Sized> {
+    genref: GenRef<'a, M, T>,
+}
+
+impl<'a, M: Mutability, T: ?Sized> GenDyn<'a, M, T> {
+    #[inline]
+    /// Wraps a `GenRef`.
+    pub fn new(genref: GenRef<'a, M, T>) -> Self {
+        GenDyn { genref }
+    }
+
+    #[inline]
+    /// Unwraps into the underlying `GenRef`.
+    pub fn into_genref(self) -> GenRef<'a, M, T> {
+        self.genref
+    }
+
+    #[inline]
+    /// Generically reborrows the reference, like `GenRef::reborrow`.
+    pub fn reborrow(&mut self) -> GenDyn<'_, M, T> {
+        GenDyn::new(GenRef::reborrow(&mut self.genref))
+    }
+}
+
+impl<'a, M: Mutability, T: ?Sized> From<GenRef<'a, M, T>> for GenDyn<'a, M, T> {
+    #[inline]
+    fn from(genref: GenRef<'a, M, T>) -> Self {
+        GenDyn::new(genref)
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a T> for GenDyn<'a, Shared, T> {
+    #[inline]
+    fn from(reference: &'a T) -> Self {
+        GenDyn::new(GenRef::from_shared(reference))
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a mut T> for GenDyn<'a, Mutable, T> {
+    #[inline]
+    fn from(reference: &'a mut T) -> Self {
+        GenDyn::new(GenRef::from_mut(reference))
+    }
+}
+
+impl<M: Mutability, T: ?Sized> Deref for GenDyn<'_, M, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.genref
+    }
+}
+
+impl<'a, M: Mutability, T: ?Sized> Reborrow for GenDyn<'a, M, T> {
+    type Reborrowed<'r>
+        = GenDyn<'r, M, T>
+    where
+        Self: 'r;
+
+    #[inline]
+    fn reborrow(&mut self) -> Self::Reborrowed<'_> {
+        GenDyn::reborrow(self)
+    }
+}
+
+impl<'a, M: Mutability, T: ?Sized> Downgrade for GenDyn<'a, M, T> {
+    type Downgraded = GenDyn<'a, Shared, T>;
+
+    #[inline]
+    fn downgrade(self) -> Self::Downgraded {
+        GenDyn::new(self.genref.downgrade())
+    }
+}
@@ -0,0 +1,218 @@
+use core::fmt;
+use core::iter::FusedIterator;
+
+use crate::mutability::transmute_unchecked;
+use crate::{GenRef, IsMutable, IsShared, Mutability, MutabilityEnum};
+
+/// A value of type `S` if `M` is `Shared`, or a value of type `Mu` if `M` is `Mutable`.
+///
+/// This is a wrapper around `M::Select<S, Mu>`, and has the same representation.
+/// Unlike `M::Select<S, Mu>`, the type checker can infer `S` and `Mu` separately, so values created in the two branches of a `match` over `M::mutability()` (or inside `gen_mut!`) unify into the same `GenEither` type.
+/// This allows returning values whose type differs between the shared and the mutable case, such as iterators or guards.
+///
+/// Inside `gen_mut!`, a value can be wrapped with `gen_either!($value)` and unwrapped with `from_either!($either)`.
+///
+/// `GenEither` implements `Iterator` when both `S` and `Mu` are iterators with the same item type:
+///
+/// ```
+/// # use generic_mutability::{gen_mut, GenRef, Mutability};
+/// fn gen_iter<'a, M: Mutability, T>(
+///     gen_slice: GenRef<'a, M, [T]>,
+/// ) -> impl Iterator<Item = GenRef<'a, M, T>> {
+///     gen_mut!(M => {
+///         let iter = switch_shared_mut![<[T]>::iter, <[T]>::iter_mut](from_gen!(gen_slice));
+///         gen_either!(iter.map(into_gen!()))
+///     })
+/// }
+/// ```
+///
+/// A `GenEither<M, &T, &mut T>` can be merged back into a `GenRef` using `into_genref`.
+#[repr(transparent)]
+pub struct GenEither<M: Mutability, S, Mu> {
+    inner: M::Select<S, Mu>,
+}
+
+impl<M: Mutability, S, Mu> GenEither<M, S, Mu> {
+    #[inline]
+    /// Wraps a value of the shared type. The proof guarantees that `M` is `Shared`.
+    pub fn new_shared(value: S, proof: IsShared<M>) -> Self {
+        GenEither {
+            inner: proof.select(value),
+        }
+    }
+
+    #[inline]
+    /// Wraps a value of the mutable type. The proof guarantees that `M` is `Mutable`.
+    pub fn new_mut(value: Mu, proof: IsMutable<M>) -> Self {
+        GenEither {
+            inner: proof.select(value),
+        }
+    }
+
+    #[inline]
+    /// Wraps an `M::Select<S, Mu>`.
+    pub fn from_select(inner: M::Select<S, Mu>) -> Self {
+        GenEither { inner }
+    }
+
+    #[inline]
+    /// Unwraps into `M::Select<S, Mu>`.
+    pub fn into_select(self) -> M::Select<S, Mu> {
+        self.inner
+    }
+
+    #[inline]
+    /// Unwraps the value of the shared type. The proof guarantees that `M` is `Shared`.
+    pub fn into_shared(self, proof: IsShared<M>) -> S {
+        proof.unselect(self.inner)
+    }
+
+    #[inline]
+    /// Unwraps the value of the mutable type. The proof guarantees that `M` is `Mutable`.
+    pub fn into_mut(self, proof: IsMutable<M>) -> Mu {
+        proof.unselect(self.inner)
+    }
+
+    #[inline]
+    /// Converts a `&GenEither<M, S, Mu>` into a `GenEither<M, &S, &Mu>`.
+    pub fn as_ref(&self) -> GenEither<M, &S, &Mu> {
+        match M::mutability() {
+            MutabilityEnum::Shared(proof) => {
+                // SAFETY: `M` is `Shared`, so `M::Select<S, Mu>` is `S`.
+                let value = unsafe { transmute_unchecked::<&M::Select<S, Mu>, &S>(&self.inner) };
+                GenEither::new_shared(value, proof)
+            }
+            MutabilityEnum::Mutable(proof) => {
+                // SAFETY: `M` is `Mutable`, so `M::Select<S, Mu>` is `Mu`.
+                let value = unsafe { transmute_unchecked::<&M::Select<S, Mu>, &Mu>(&self.inner) };
+                GenEither::new_mut(value, proof)
+            }
+        }
+    }
+
+    #[inline]
+    /// Converts a `&mut GenEither<M, S, Mu>` into a `GenEither<M, &mut S, &mut Mu>`.
+    pub fn as_mut(&mut self) -> GenEither<M, &mut S, &mut Mu> {
+        match M::mutability() {
+            MutabilityEnum::Shared(proof) => {
+                // SAFETY: `M` is `Shared`, so `M::Select<S, Mu>` is `S`.
+                let value = unsafe {
+                    transmute_unchecked::<&mut M::Select<S, Mu>, &mut S>(&mut self.inner)
+                };
+                GenEither::new_shared(value, proof)
+            }
+            MutabilityEnum::Mutable(proof) => {
+                // SAFETY: `M` is `Mutable`, so `M::Select<S, Mu>` is `Mu`.
+                let value = unsafe {
+                    transmute_unchecked::<&mut M::Select<S, Mu>, &mut Mu>(&mut self.inner)
+                };
+                GenEither::new_mut(value, proof)
+            }
+        }
+    }
+
+    #[inline]
+    /// Maps the contained value with `f_shared` if `M` is `Shared`, or with `f_mut` if `M` is `Mutable`.
+    pub fn map_either<S2, Mu2>(
+        self,
+        f_shared: impl FnOnce(S) -> S2,
+        f_mut: impl FnOnce(Mu) -> Mu2,
+    ) -> GenEither<M, S2, Mu2> {
+        match M::mutability() {
+            MutabilityEnum::Shared(proof) => {
+                GenEither::new_shared(f_shared(self.into_shared(proof)), proof)
+            }
+            MutabilityEnum::Mutable(proof) => {
+                GenEither::new_mut(f_mut(self.into_mut(proof)), proof)
+            }
+        }
+    }
+
+    #[inline]
+    /// Merges the two cases into a single type, calling `f_shared` if `M` is `Shared`, or `f_mut` if `M` is `Mutable`.
+    pub fn merge<R>(self, f_shared: impl FnOnce(S) -> R, f_mut: impl FnOnce(Mu) -> R) -> R {
+        match M::mutability() {
+            MutabilityEnum::Shared(proof) => f_shared(self.into_shared(proof)),
+            MutabilityEnum::Mutable(proof) => f_mut(self.into_mut(proof)),
+        }
+    }
+}
+
+impl<'s, M: Mutability, T: ?Sized> GenEither<M, &'s T, &'s mut T> {
+    #[inline]
+    /// Merges a `&T` or `&mut T` back into a `GenRef<'_, M, T>`.
+    pub fn into_genref(self) -> GenRef<'s, M, T> {
+        match M::mutability() {
+            MutabilityEnum::Shared(proof) => {
+                GenRef::gen_from_shared(self.into_shared(proof), proof)
+            }
+            MutabilityEnum::Mutable(proof) => GenRef::gen_from_mut(self.into_mut(proof), proof),
+        }
+    }
+}
+
+impl<'s, M: Mutability, T: ?Sized> From<GenRef<'s, M, T>> for GenEither<M, &'s T, &'s mut T> {
+    #[inline]
+    fn from(genref: GenRef<'s, M, T>) -> Self {
+        match M::mutability() {
+            MutabilityEnum::Shared(proof) => {
+                GenEither::new_shared(GenRef::gen_into_shared(genref, proof), proof)
+            }
+            MutabilityEnum::Mutable(proof) => {
+                GenEither::new_mut(GenRef::gen_into_mut(genref, proof), proof)
+            }
+        }
+    }
+}
+
+impl<M: Mutability, S, Mu> fmt::Debug for GenEither<M, S, Mu>
+where
+    S: fmt::Debug,
+    Mu: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match M::mutability() {
+            MutabilityEnum::Shared(proof) => self.as_ref().into_shared(proof).fmt(f),
+            MutabilityEnum::Mutable(proof) => self.as_ref().into_mut(proof).fmt(f),
+        }
+    }
+}
+
+impl<M: Mutability, S, Mu> Iterator for GenEither<M, S, Mu>
+where
+    S: Iterator,
+    Mu: Iterator<Item = S::Item>,
+{
+    type Item = S::Item;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.as_mut().merge(S::next, Mu::next)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.as_ref().merge(S::size_hint, Mu::size_hint)
+    }
+}
+
+impl<M: Mutability, S, Mu> DoubleEndedIterator for GenEither<M, S, Mu>
+where
+    S: DoubleEndedIterator,
+    Mu: DoubleEndedIterator<Item = S::Item>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.as_mut().merge(S::next_back, Mu::next_back)
+    }
+}
+
+impl<M: Mutability, S, Mu> ExactSizeIterator for GenEither<M, S, Mu>
+where
+    S: ExactSizeIterator,
+    Mu: ExactSizeIterator<Item = S::Item>,
+{
+}
+
+impl<M: Mutability, S, Mu> FusedIterator for GenEither<M, S, Mu>
+where
+    S: FusedIterator,
+    Mu: FusedIterator<Item = S::Item>,
+{
+}
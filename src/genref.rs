@@ -4,8 +4,11 @@ use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
 
-use crate::mutability::{IsMutable, IsShared, Mutability, Mutable, Shared};
+use crate::mutability::{
+    transmute_unchecked, IsMutable, IsShared, Min, Mutability, Mutable, Shared,
+};
 
+pub mod dispatch;
 pub mod genref_methods;
 mod impl_traits;
 
@@ -118,10 +121,52 @@ mod impl_traits;
 ///     field!(&gen gen_slice[index])
 /// }
 /// ```
+///
+/// # Variance
+///
+/// `GenRef<'s, M, T>` is covariant in `'s`, just like `&'s T` and `&'s mut T`:
+///
+/// ```
+/// # use generic_mutability::{GenRef, Mutability};
+/// fn shorten<'a, M: Mutability>(genref: GenRef<'static, M, i32>) -> GenRef<'a, M, i32> {
+///     genref
+/// }
+/// ```
+///
+/// However, it is invariant in `T` for every mutability, including `Shared`.
+/// Variance is a property of the type definition and not of its instantiations, so `GenRef<'_, Shared, T>` can not be covariant in `T` while `GenRef<'_, Mutable, T>` is invariant.
+/// As `GenRef<'_, Mutable, T>` must be invariant (like `&mut T`) for soundness, this also applies to `Shared`:
+///
+/// ```compile_fail
+/// # use generic_mutability::{GenRef, Shared};
+/// fn shorten<'a, 'b>(genref: GenRef<'b, Shared, &'static str>) -> GenRef<'b, Shared, &'a str> {
+///     genref
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use generic_mutability::{GenRef, Mutable};
+/// fn shorten<'a, 'b>(genref: GenRef<'b, Mutable, &'static str>) -> GenRef<'b, Mutable, &'a str> {
+///     genref
+/// }
+/// ```
+///
+/// In non-generic code, a `GenRef<'_, Shared, T>` can be converted into a shared reference, which is covariant:
+///
+/// ```
+/// # use generic_mutability::{GenRef, Shared};
+/// fn shorten<'a, 'b>(genref: GenRef<'b, Shared, &'static str>) -> GenRef<'b, Shared, &'a str> {
+///     GenRef::from(GenRef::into_shared(genref))
+/// }
+/// ```
 #[repr(transparent)]
+#[allow(clippy::doc_overindented_list_items)]
 pub struct GenRef<'s, M: Mutability, T: ?Sized> {
     // This could contain an `ErasedMutRef` instead of `_lifetime` and `ptr`,
     // but that way it could not implement `Copy`
+    //
+    // `&'s mut T` makes `GenRef` invariant in `T`, which is required when `M` is `Mutable`.
+    // See the "Variance" section in the documentation.
     _lifetime: PhantomData<&'s mut T>,
     _mutability: PhantomData<*const M>,
     ptr: NonNull<T>,
@@ -139,6 +184,19 @@ The exact semantics of this depend on the memory model adopted by Rust.
 # Guarantees
 
 The returned pointer is guaranteed to be valid for reads for `'s`, and also for writes if `M` is `Mutable`."
+    };
+    (as_native_ptr) => {
+         "Casts the reference into a native raw pointer, `*const T` if `M` is `Shared` or `*mut T` if `M` is `Mutable`.
+This is available in a generic context.
+
+This is mostly useful for FFI. The same safety considerations and guarantees apply as for `as_ptr`."
+    };
+    (into_native) => {
+         "Converts a generic `GenRef<'_, M, T>` into the native reference type `M::Ref<'_, T>`.
+This is available in a generic context.
+
+`M::Ref<'s, T>` is `&'s T` if `M` is `Shared` and `&'s mut T` if `M` is `Mutable`.
+The result can be converted back into a `GenRef` using the `from_native` function."
     };
     (gen_into_shared_downgrading) => {
          "Converts a generic `GenRef<'_, M, T>` into `&T`, downgrading the reference if `M` is `Mutable`.
@@ -180,14 +238,34 @@ This is available in a generic context.
 Using this function is usually sufficient.
 For mapping over field access, you can use the `field!` macro instead.
 If you need more flexibility, you can use the `gen_mut!` macro or `match`ing over `M::mutability()`."
+    };
+    (try_map) => {
+         "Maps a generic `GenRef` into an optional one using either `f_mut` or `f_shared`.
+This is available in a generic context.
+
+This is the fallible variant of `map`, used for projections that may not succeed, such as optional fields or enum variants.
+For these projections, you can use the `field!` macro instead."
+    };
+    (split_tuple) => {
+         "Splits a `GenRef` to a tuple into a tuple of `GenRef`s to each element.
+This is available in a generic context.
+
+This is implemented for tuples of up to 12 elements.
+To split a struct or a subset of a tuple, you can use the `fields!` macro instead."
     };
     (map_deref) => {
          "Generically dereferences the value contained in the `GenRef`.
 This is available in a generic context."
     };
+    (weaken) => {
+         "Weakens the mutability of a `GenRef` to `Min<M, N>`, downgrading the reference unless both `M` and `N` are `Mutable`.
+This is available in a generic context.
+
+This is useful when the result of an operation is derived from both a `GenRef<'_, M, _>` and a `GenRef<'_, N, _>`, as it can only be as mutable as the weaker of the two."
+    };
 }
 
-pub(self) use docs_for;
+use docs_for;
 
 impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
     #[inline]
@@ -212,7 +290,8 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
     ///     - If the mutability is `Mutable`:
     ///         - The pointer must be valid for reads and writes for lifetime `'s`.
     ///         - The pointed-to value must not be accessed (read or written) by other pointers, and no other references to it may exist during `'s`.
-    pub unsafe fn from_ptr_unchecked(ptr: NonNull<T>) -> Self {
+    #[allow(clippy::doc_overindented_list_items)]
+    pub const unsafe fn from_ptr_unchecked(ptr: NonNull<T>) -> Self {
         Self {
             _lifetime: PhantomData,
             _mutability: PhantomData,
@@ -222,17 +301,74 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
 
     #[inline]
     #[doc = docs_for!(as_ptr)]
-    pub fn as_ptr(genref: &Self) -> NonNull<T> {
+    pub const fn as_ptr(genref: &Self) -> NonNull<T> {
         genref.ptr
     }
 
+    #[inline]
+    #[doc = docs_for!(as_native_ptr)]
+    pub fn as_native_ptr(genref: &Self) -> M::Ptr<T> {
+        use crate::MutabilityEnum::*;
+
+        let ptr = GenRef::as_ptr(genref).as_ptr();
+        match M::mutability() {
+            // SAFETY: `M` is `Mutable`, so `M::Ptr<T>` is `*mut T`.
+            Mutable(_) => unsafe { transmute_unchecked::<*mut T, M::Ptr<T>>(ptr) },
+            // SAFETY: `M` is `Shared`, so `M::Ptr<T>` is `*const T`.
+            Shared(_) => unsafe { transmute_unchecked::<*const T, M::Ptr<T>>(ptr) },
+        }
+    }
+
+    #[inline]
+    #[doc = docs_for!(into_native)]
+    pub fn into_native(genref: Self) -> M::Ref<'s, T> {
+        use crate::MutabilityEnum::*;
+
+        match M::mutability() {
+            Mutable(proof) => {
+                let reference = GenRef::gen_into_mut(genref, proof);
+                // SAFETY: `M` is `Mutable`, so `M::Ref<'s, T>` is `&'s mut T`.
+                unsafe { transmute_unchecked::<&'s mut T, M::Ref<'s, T>>(reference) }
+            }
+            Shared(proof) => {
+                let reference = GenRef::gen_into_shared(genref, proof);
+                // SAFETY: `M` is `Shared`, so `M::Ref<'s, T>` is `&'s T`.
+                unsafe { transmute_unchecked::<&'s T, M::Ref<'s, T>>(reference) }
+            }
+        }
+    }
+
+    #[inline]
+    /// Converts the native reference type `M::Ref<'_, T>` into a generic `GenRef<'_, M, T>`.
+    /// This is available in a generic context.
+    ///
+    /// `M::Ref<'s, T>` is `&'s T` if `M` is `Shared` and `&'s mut T` if `M` is `Mutable`, so unlike `gen_from_shared` and `gen_from_mut`, this does not require a proof.
+    pub fn from_native(reference: M::Ref<'s, T>) -> Self {
+        use crate::MutabilityEnum::*;
+
+        match M::mutability() {
+            Mutable(proof) => {
+                // SAFETY: `M` is `Mutable`, so `M::Ref<'s, T>` is `&'s mut T`.
+                let reference =
+                    unsafe { transmute_unchecked::<M::Ref<'s, T>, &'s mut T>(reference) };
+                GenRef::gen_from_mut(reference, proof)
+            }
+            Shared(proof) => {
+                // SAFETY: `M` is `Shared`, so `M::Ref<'s, T>` is `&'s T`.
+                let reference = unsafe { transmute_unchecked::<M::Ref<'s, T>, &'s T>(reference) };
+                GenRef::gen_from_shared(reference, proof)
+            }
+        }
+    }
+
     #[inline]
     /// Converts a `&mut T` into a generic `GenRef<'_, M, T>`, downgrading the reference if `M` is `Shared`.
     ///
     /// If `M` is `Mutable` it behaves exactly the same way as `gen_from_mut` without requiring a proof for mutability.
     /// In this case, the difference between the two is purely semantic: if you have proof that `M` is `Mutable`, you should use `gen_from_mut`.
-    pub fn gen_from_mut_downgrading(reference: &'s mut T) -> Self {
-        let ptr = NonNull::from(reference);
+    pub const fn gen_from_mut_downgrading(reference: &'s mut T) -> Self {
+        // SAFETY: a pointer derived from a reference is never null.
+        let ptr = unsafe { NonNull::new_unchecked(reference as *mut T) };
 
         // SAFETY: `ptr` is derived from a mutable reference, so points to a valid `T` and is valid and unaliased for `'s`.
         // The correct lifetime is enforced by the function signature.
@@ -241,7 +377,7 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
 
     #[inline]
     #[doc = docs_for!(gen_into_shared_downgrading)]
-    pub fn gen_into_shared_downgrading(genref: Self) -> &'s T {
+    pub const fn gen_into_shared_downgrading(genref: Self) -> &'s T {
         let ptr = GenRef::as_ptr(&genref);
 
         // SAFETY: `GenRef::as_ptr` guarantees that `ptr` points to a valid `T` and is valid for reads for `'s`.
@@ -250,7 +386,7 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
 
     #[inline]
     #[doc = docs_for!(gen_into_mut)]
-    pub fn gen_into_mut(genref: Self, _proof: IsMutable<M>) -> &'s mut T {
+    pub const fn gen_into_mut(genref: Self, _proof: IsMutable<M>) -> &'s mut T {
         let mut ptr = GenRef::as_ptr(&genref);
 
         // SAFETY: For a value of `IsMutable<M>` to exist, `M` must be `Mutable`.
@@ -266,14 +402,14 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
     /// That can be obtained by `match`ing on `M::mutability()`.
     ///
     /// If you want to force the conversion even if `M` is `Shared`, you can use the `gen_from_mut_downgrading` function.
-    pub fn gen_from_mut(reference: &'s mut T, _proof: IsMutable<M>) -> Self {
+    pub const fn gen_from_mut(reference: &'s mut T, _proof: IsMutable<M>) -> Self {
         // `gen_from_mut_downgrading` is semantically different, but when called with `M = Mutable` they perform the same operation.
         GenRef::gen_from_mut_downgrading(reference)
     }
 
     #[inline]
     #[doc = docs_for!(gen_into_shared)]
-    pub fn gen_into_shared(genref: Self, _proof: IsShared<M>) -> &'s T {
+    pub const fn gen_into_shared(genref: Self, _proof: IsShared<M>) -> &'s T {
         // `gen_into_shared_downgrading` is semantically different, but when called with `M = Shared` they perform the same operation.
         GenRef::gen_into_shared_downgrading(genref)
     }
@@ -284,8 +420,9 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
     ///
     /// The conversion requires that `M` is `Shared`, this must be proven by passing an `IsShared<M>` value.
     /// That can be obtained by `match`ing on `M::mutability()`.
-    pub fn gen_from_shared(reference: &'s T, _proof: IsShared<M>) -> Self {
-        let ptr = NonNull::from(reference);
+    pub const fn gen_from_shared(reference: &'s T, _proof: IsShared<M>) -> Self {
+        // SAFETY: a pointer derived from a reference is never null.
+        let ptr = unsafe { NonNull::new_unchecked(reference as *const T as *mut T) };
 
         // SAFETY: For a value of `IsShared<M>` to exist, `M` must be `Shared`. `ptr` is derived from a shared reference, so it is valid for reads for `'s`.
         // The correct lifetime is enforced by the function signature.
@@ -294,7 +431,7 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
 
     #[inline]
     #[doc = docs_for!(reborrow)]
-    pub fn reborrow(genref: &mut Self) -> GenRef<'_, M, T> {
+    pub const fn reborrow(genref: &mut Self) -> GenRef<'_, M, T> {
         // SAFETY: `GenRef::as_ptr` guarantees that `ptr` points to a valid `T` and is valid for reads for `'_`. If `M` is `Mutable`, it also guarantees validity for writes.
         // The `GenRef` received as argument is not used after calling `as_ptr` for the lifetime `'_`, and the pointer returned by `as_ptr` can not be aliased, both guaranteed by the exclusive reference received as argument.
         // The correct lifetime and mutability parameters are enforced by the function signature.
@@ -320,6 +457,32 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
         }
     }
 
+    #[inline]
+    #[doc = docs_for!(try_map)]
+    pub fn try_map<U: ?Sized>(
+        genref: Self,
+        f_shared: impl FnOnce(&T) -> Option<&U>,
+        f_mut: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Option<GenRef<'s, M, U>> {
+        use crate::MutabilityEnum::*;
+
+        match M::mutability() {
+            Mutable(proof) => f_mut(GenRef::gen_into_mut(genref, proof))
+                .map(|reference| GenRef::gen_from_mut(reference, proof)),
+            Shared(proof) => f_shared(GenRef::gen_into_shared(genref, proof))
+                .map(|reference| GenRef::gen_from_shared(reference, proof)),
+        }
+    }
+
+    #[inline]
+    #[doc = docs_for!(split_tuple)]
+    pub fn split_tuple(genref: Self) -> T::Split
+    where
+        T: SplitTuple<'s, M>,
+    {
+        T::split(genref)
+    }
+
     #[inline]
     #[doc = docs_for!(map_deref)]
     pub fn map_deref(genref: Self) -> GenRef<'s, M, T::Target>
@@ -328,8 +491,63 @@ impl<'s, M: Mutability, T: ?Sized> GenRef<'s, M, T> {
     {
         GenRef::map(genref, Deref::deref, DerefMut::deref_mut)
     }
+
+    #[inline]
+    #[doc = docs_for!(weaken)]
+    pub fn weaken<N: Mutability>(genref: Self) -> GenRef<'s, Min<M, N>, T> {
+        use crate::MutabilityEnum::*;
+
+        match <Min<M, N>>::mutability() {
+            Mutable(proof) => {
+                let (proof_m, _) = IsMutable::split_min::<N>(proof);
+                GenRef::gen_from_mut(GenRef::gen_into_mut(genref, proof_m), proof)
+            }
+            Shared(proof) => {
+                GenRef::gen_from_shared(GenRef::gen_into_shared_downgrading(genref), proof)
+            }
+        }
+    }
 }
 
+impl<'s, M: Mutability, T> GenRef<'s, M, Option<T>> {
+    #[inline]
+    /// Converts a `GenRef<'_, M, Option<T>>` into an `Option<GenRef<'_, M, T>>`.
+    /// This is available in a generic context.
+    ///
+    /// This is the generic equivalent of `Option::as_ref` and `Option::as_mut`.
+    pub fn transpose(genref: Self) -> Option<GenRef<'s, M, T>> {
+        GenRef::try_map(genref, Option::as_ref, Option::as_mut)
+    }
+}
+
+/// Tuple types that a `GenRef` can be split into a tuple of `GenRef`s to each element of.
+///
+/// This is used as a bound on `GenRef::split_tuple`, and is implemented for tuples of up to 12 elements.
+pub trait SplitTuple<'s, M: Mutability> {
+    /// The tuple of `GenRef`s to each element.
+    type Split;
+
+    /// Splits the `GenRef` into a tuple of `GenRef`s to each element.
+    fn split(genref: GenRef<'s, M, Self>) -> Self::Split;
+}
+
+macro_rules! impl_split_tuple {
+    ([$($done:ident $done_index:tt)*] $name:ident $index:tt $(, $rest:ident $rest_index:tt)*) => {
+        impl<'s, M: Mutability, $($done: 's,)* $name: 's> SplitTuple<'s, M> for ($($done,)* $name,) {
+            type Split = ($(GenRef<'s, M, $done>,)* GenRef<'s, M, $name>,);
+
+            #[inline]
+            fn split(genref: GenRef<'s, M, Self>) -> Self::Split {
+                crate::fields!(&gen genref => $($done_index,)* $index)
+            }
+        }
+        impl_split_tuple!([$($done $done_index)* $name $index] $($rest $rest_index),*);
+    };
+    ([$($done:ident $done_index:tt)*]) => {};
+}
+
+impl_split_tuple!([] A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
 impl<'s, T: ?Sized> GenRef<'s, Shared, T> {
     #[inline]
     /// Converts a `GenRef<'_, Shared, T>` into `&T` in a non-generic context.
@@ -337,9 +555,17 @@ impl<'s, T: ?Sized> GenRef<'s, Shared, T> {
     /// This is used to unwrap the reference in end-user code.
     ///
     /// To perform the same operation in a generic context, use `gen_into_shared` or `gen_into_shared_downgrading`.
-    pub fn into_shared(genref: Self) -> &'s T {
+    pub const fn into_shared(genref: Self) -> &'s T {
         Self::gen_into_shared(genref, Shared::mutability())
     }
+
+    #[inline]
+    /// Creates a non-generic `GenRef<'_, Shared, T>` from a `&T`.
+    ///
+    /// This is the same as the `From<&T>` implementation, but it is also available in `const` contexts.
+    pub const fn from_shared(reference: &'s T) -> Self {
+        Self::gen_from_shared(reference, Shared::mutability())
+    }
 }
 impl<'s, T: ?Sized> GenRef<'s, Mutable, T> {
     #[inline]
@@ -348,9 +574,17 @@ impl<'s, T: ?Sized> GenRef<'s, Mutable, T> {
     /// This is used to unwrap the reference in end-user code.
     ///
     /// To perform the same operation in a generic context, use `gen_into_mut`.
-    pub fn into_mut(genref: Self) -> &'s mut T {
+    pub const fn into_mut(genref: Self) -> &'s mut T {
         Self::gen_into_mut(genref, Mutable::mutability())
     }
+
+    #[inline]
+    /// Creates a non-generic `GenRef<'_, Mutable, T>` from a `&mut T`.
+    ///
+    /// This is the same as the `From<&mut T>` implementation, but it is also available in `const` contexts.
+    pub const fn from_mut(reference: &'s mut T) -> Self {
+        Self::gen_from_mut(reference, Mutable::mutability())
+    }
 }
 
 /// Creates a non-generic `GenRef<'_, Shared, T>` from a `&T`.
@@ -361,7 +595,7 @@ impl<'s, T: ?Sized> GenRef<'s, Mutable, T> {
 impl<'s, T: ?Sized> From<&'s T> for GenRef<'s, Shared, T> {
     #[inline]
     fn from(reference: &'s T) -> Self {
-        GenRef::gen_from_shared(reference, Shared::mutability())
+        GenRef::from_shared(reference)
     }
 }
 /// Creates a non-generic `GenRef<'_, Mutable, T>` from a `&mut T`.
@@ -372,7 +606,7 @@ impl<'s, T: ?Sized> From<&'s T> for GenRef<'s, Shared, T> {
 impl<'s, T: ?Sized> From<&'s mut T> for GenRef<'s, Mutable, T> {
     #[inline]
     fn from(reference: &'s mut T) -> Self {
-        GenRef::gen_from_mut(reference, Mutable::mutability())
+        GenRef::from_mut(reference)
     }
 }
 
@@ -431,9 +665,36 @@ impl<M: Mutability, T: ?Sized> fmt::Pointer for GenRef<'_, M, T> {
     }
 }
 
-/// This implementation requires `T: Sync` even when `M` is `Mutable`.
-/// With specialisation, this requirement could be lifted.
-// SAFETY: `GenRef` behaves like a reference, and both `&T` and `&mut T` implement `Send` if `T` is `Send` and `Sync`
-unsafe impl<M: Mutability, T: ?Sized> Send for GenRef<'_, M, T> where T: Send + Sync {}
+/// `GenRef<'s, M, T>` is `Send` exactly when the native reference `M::Ref<'s, T>` is:
+/// `GenRef<'_, Shared, T>` requires `T: Sync` (like `&T`), and `GenRef<'_, Mutable, T>` requires `T: Send` (like `&mut T`).
+///
+/// In a generic context, neither `T: Send` nor `T: Sync` is sufficient on its own (not even together), use an `M::Ref<'s, T>: Send` (or `GenRef<'s, M, T>: Send`) bound instead.
+///
+/// ```
+/// # use core::cell::Cell;
+/// # use generic_mutability::{GenRef, Mutability, Mutable};
+/// fn assert_send<T: Send>(_: T) {}
+///
+/// fn gen_send<'s, M: Mutability, T>(genref: GenRef<'s, M, T>)
+/// where
+///     GenRef<'s, M, T>: Send,
+/// {
+///     assert_send(genref);
+/// }
+///
+/// let mut cell = Cell::new(1);
+/// gen_send(GenRef::<'_, Mutable, _>::from(&mut cell));
+/// ```
+///
+/// ```compile_fail,E0277
+/// # use core::cell::Cell;
+/// # use generic_mutability::{GenRef, Shared};
+/// fn assert_send<T: Send>(_: T) {}
+///
+/// let cell = Cell::new(1);
+/// assert_send(GenRef::<'_, Shared, _>::from(&cell));
+/// ```
+// SAFETY: `GenRef` behaves like `M::Ref<'s, T>`, which is `&'s T` or `&'s mut T` depending on `M`, so it can be sent to another thread whenever that reference can.
+unsafe impl<'s, M: Mutability, T: ?Sized> Send for GenRef<'s, M, T> where M::Ref<'s, T>: Send {}
 // SAFETY: `GenRef` behaves like a reference, and both `&T` and `&mut T` implement `Sync` if `T` is `Sync`
 unsafe impl<M: Mutability, T: ?Sized> Sync for GenRef<'_, M, T> where T: Sync {}
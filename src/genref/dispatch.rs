@@ -0,0 +1,154 @@
+//! Dispatch traits allow `GenRef<'s, M, T>` to implement a trait for generic `M` when the native reference `M::Ref<'s, T>` implements it.
+//!
+//! Such traits can not be implemented directly with an `M::Ref<'s, T>: Trait` bound, as the implementation has to call the trait on a reborrowed `GenRef` with a shorter lifetime.
+//! Instead, each dispatch trait is implemented for `Shared` and `Mutable` separately, with the bound that is appropriate for that mutability:
+//! `Shared` requires the trait to be implemented for `&'s T`, while `Mutable` requires it for `T` (which `&mut T` forwards to).
+//!
+//! The forwarding implementation on `GenRef` is then bounded by `M: Dispatch<'s, T>`.
+//! You should use the same bound when you need the trait in a generic context.
+
+#[cfg(any(feature = "std", doc))]
+extern crate std;
+
+use crate::{GenRef, Mutability, Mutable, Shared};
+
+/// Dispatches `Iterator` for `GenRef<'s, M, T>` to `&'s T` if `M` is `Shared`, or to `T` if `M` is `Mutable`.
+///
+/// This is implemented for `Shared` when `&'s T: Iterator`, and for `Mutable` when `T: Iterator`.
+/// The forwarding implementation on `GenRef` is bounded by this trait, which is the bound you need to use in a generic context.
+pub trait IteratorDispatch<'s, T: ?Sized>: Mutability {
+    /// The type of the elements being iterated over.
+    type Item;
+
+    /// Advances `genref` and returns the next value.
+    fn next(genref: &mut GenRef<'s, Self, T>) -> Option<Self::Item>;
+
+    /// Returns the `n`th element of `genref`.
+    fn nth(genref: &mut GenRef<'s, Self, T>, n: usize) -> Option<Self::Item>;
+
+    /// Returns the bounds on the remaining length of `genref`.
+    fn size_hint(genref: &GenRef<'s, Self, T>) -> (usize, Option<usize>);
+}
+
+/// Implementations that advance the reference itself are supported: the advanced reference is stored back into the `GenRef`.
+impl<'s, T: ?Sized + 's> IteratorDispatch<'s, T> for Shared
+where
+    &'s T: Iterator,
+{
+    type Item = <&'s T as Iterator>::Item;
+    fn next(genref: &mut GenRef<'s, Self, T>) -> Option<Self::Item> {
+        let mut reference = GenRef::into_shared(*genref);
+        let item = Iterator::next(&mut reference);
+        *genref = GenRef::from_shared(reference);
+        item
+    }
+    fn nth(genref: &mut GenRef<'s, Self, T>, n: usize) -> Option<Self::Item> {
+        let mut reference = GenRef::into_shared(*genref);
+        let item = Iterator::nth(&mut reference, n);
+        *genref = GenRef::from_shared(reference);
+        item
+    }
+    fn size_hint(genref: &GenRef<'s, Self, T>) -> (usize, Option<usize>) {
+        Iterator::size_hint(&GenRef::into_shared(*genref))
+    }
+}
+
+impl<'s, T: ?Sized> IteratorDispatch<'s, T> for Mutable
+where
+    T: Iterator,
+{
+    type Item = T::Item;
+    fn next(genref: &mut GenRef<'s, Self, T>) -> Option<Self::Item> {
+        T::next(&mut **genref)
+    }
+    fn nth(genref: &mut GenRef<'s, Self, T>, n: usize) -> Option<Self::Item> {
+        T::nth(&mut **genref, n)
+    }
+    fn size_hint(genref: &GenRef<'s, Self, T>) -> (usize, Option<usize>) {
+        T::size_hint(&**genref)
+    }
+}
+
+#[cfg(any(feature = "std", doc))]
+/// Dispatches `std::io::Read` for `GenRef<'s, M, T>` to `&'s T` if `M` is `Shared`, or to `T` if `M` is `Mutable`.
+///
+/// This is implemented for `Shared` when `&'s T: Read` (for example `File` or `TcpStream`), and for `Mutable` when `T: Read`.
+/// The forwarding implementations on `GenRef` are bounded by this trait, which is the bound you need to use in a generic context.
+///
+/// This is only available with the feature flag `std`.
+pub trait ReadDispatch<'s, T: ?Sized>: Mutability {
+    /// Reads from `genref` into `buf`.
+    fn read(genref: &mut GenRef<'s, Self, T>, buf: &mut [u8]) -> std::io::Result<usize>;
+}
+
+#[cfg(any(feature = "std", doc))]
+/// Implementations that advance the reference itself (like `&[u8]`) are supported: the advanced reference is stored back into the `GenRef`.
+impl<'s, T: ?Sized + 's> ReadDispatch<'s, T> for Shared
+where
+    &'s T: std::io::Read,
+{
+    fn read(genref: &mut GenRef<'s, Self, T>, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut reference = GenRef::into_shared(*genref);
+        let result = std::io::Read::read(&mut reference, buf);
+        *genref = GenRef::from_shared(reference);
+        result
+    }
+}
+
+#[cfg(any(feature = "std", doc))]
+impl<'s, T: ?Sized> ReadDispatch<'s, T> for Mutable
+where
+    T: std::io::Read,
+{
+    fn read(genref: &mut GenRef<'s, Self, T>, buf: &mut [u8]) -> std::io::Result<usize> {
+        T::read(&mut **genref, buf)
+    }
+}
+
+#[cfg(any(feature = "std", doc))]
+/// Dispatches `std::io::Write` for `GenRef<'s, M, T>` to `&'s T` if `M` is `Shared`, or to `T` if `M` is `Mutable`.
+///
+/// This is implemented for `Shared` when `&'s T: Write` (for example `File` or `TcpStream`), and for `Mutable` when `T: Write`.
+/// The forwarding implementations on `GenRef` are bounded by this trait, which is the bound you need to use in a generic context.
+///
+/// This is only available with the feature flag `std`.
+pub trait WriteDispatch<'s, T: ?Sized>: Mutability {
+    /// Writes `buf` into `genref`.
+    fn write(genref: &mut GenRef<'s, Self, T>, buf: &[u8]) -> std::io::Result<usize>;
+
+    /// Flushes `genref`.
+    fn flush(genref: &mut GenRef<'s, Self, T>) -> std::io::Result<()>;
+}
+
+#[cfg(any(feature = "std", doc))]
+/// Like for `Read`, the reference is stored back into the `GenRef` after every call.
+impl<'s, T: ?Sized + 's> WriteDispatch<'s, T> for Shared
+where
+    &'s T: std::io::Write,
+{
+    fn write(genref: &mut GenRef<'s, Self, T>, buf: &[u8]) -> std::io::Result<usize> {
+        let mut reference = GenRef::into_shared(*genref);
+        let result = std::io::Write::write(&mut reference, buf);
+        *genref = GenRef::from_shared(reference);
+        result
+    }
+    fn flush(genref: &mut GenRef<'s, Self, T>) -> std::io::Result<()> {
+        let mut reference = GenRef::into_shared(*genref);
+        let result = std::io::Write::flush(&mut reference);
+        *genref = GenRef::from_shared(reference);
+        result
+    }
+}
+
+#[cfg(any(feature = "std", doc))]
+impl<'s, T: ?Sized> WriteDispatch<'s, T> for Mutable
+where
+    T: std::io::Write,
+{
+    fn write(genref: &mut GenRef<'s, Self, T>, buf: &[u8]) -> std::io::Result<usize> {
+        T::write(&mut **genref, buf)
+    }
+    fn flush(genref: &mut GenRef<'s, Self, T>) -> std::io::Result<()> {
+        T::flush(&mut **genref)
+    }
+}
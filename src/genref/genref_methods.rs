@@ -1,5 +1,5 @@
 use super::docs_for;
-use crate::{GenRef, IsMutable, IsShared, Mutability};
+use crate::{GenRef, IsMutable, IsShared, Min, Mutability, Reborrow, SplitTuple};
 use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
 
@@ -18,11 +18,32 @@ mod seal {
 /// This trait is only implemented for `GenRef<'_, M, T>` and is sealed so no other types can implement it.
 ///
 /// In theory, it is also possible to receive `impl GenRefMethods<'_, M, T>` instead of `GenRef<'_, M, T>` as an argument, which disables the `Deref` impl, although it is more confusing from the caller side.
+///
+/// The method variant of `GenRef::reborrow` is provided by the [`Reborrow`](crate::Reborrow) trait, which is also implemented for other types holding `GenRef`s.
+/// `GenRefMethods::reborrow` is deprecated and only kept for existing callers; when both traits are in scope, call `Reborrow::reborrow` (or `GenRef::reborrow`) explicitly, as `.reborrow()` is ambiguous.
+///
+/// ```rust
+/// use generic_mutability::{GenRef, GenRefMethods, Mutability};
+///
+/// #[allow(deprecated)]
+/// fn gen_first<M: Mutability>(mut slice: GenRef<'_, M, [u8]>) -> GenRef<'_, M, u8> {
+///     let _len = slice.reborrow().deref().len();
+///     slice.map(|slice| &mut slice[0], |slice| &slice[0])
+/// }
+/// ```
 pub trait GenRefMethods<'s, M: Mutability, T: ?Sized>: seal::Sealed {
     /// This is a method variant of the equivalent associated function on `GenRef`.
     #[doc = docs_for!(as_ptr)]
     fn as_ptr(&self) -> NonNull<T>;
 
+    /// This is a method variant of the equivalent associated function on `GenRef`.
+    #[doc = docs_for!(as_native_ptr)]
+    fn as_native_ptr(&self) -> M::Ptr<T>;
+
+    /// This is a method variant of the equivalent associated function on `GenRef`.
+    #[doc = docs_for!(into_native)]
+    fn into_native(self) -> M::Ref<'s, T>;
+
     /// This is a method variant of the equivalent associated function on `GenRef`.
     #[doc = docs_for!(gen_into_shared_downgrading)]
     fn gen_into_shared_downgrading(self) -> &'s T;
@@ -36,6 +57,7 @@ pub trait GenRefMethods<'s, M: Mutability, T: ?Sized>: seal::Sealed {
     fn gen_into_shared(self, _proof: IsShared<M>) -> &'s T;
     /// This is a method variant of the equivalent associated function on `GenRef`.
     #[doc = docs_for!(reborrow)]
+    #[deprecated(note = "use `Reborrow::reborrow` instead")]
     fn reborrow(&mut self) -> GenRef<'_, M, T>;
 
     /// This is a method variant of the equivalent associated function on `GenRef`.
@@ -46,12 +68,30 @@ pub trait GenRefMethods<'s, M: Mutability, T: ?Sized>: seal::Sealed {
         f_shared: impl FnOnce(&T) -> &U,
     ) -> GenRef<'s, M, U>;
 
+    /// This is a method variant of the equivalent associated function on `GenRef`.
+    #[doc = docs_for!(try_map)]
+    fn try_map<U: ?Sized>(
+        self,
+        f_mut: impl FnOnce(&mut T) -> Option<&mut U>,
+        f_shared: impl FnOnce(&T) -> Option<&U>,
+    ) -> Option<GenRef<'s, M, U>>;
+
+    /// This is a method variant of the equivalent associated function on `GenRef`.
+    #[doc = docs_for!(split_tuple)]
+    fn split_tuple(self) -> T::Split
+    where
+        T: SplitTuple<'s, M>;
+
     /// This is a method variant of the equivalent associated function on `GenRef`.
     #[doc = docs_for!(map_deref)]
     fn map_deref(self) -> GenRef<'s, M, T::Target>
     where
         T: Deref + DerefMut;
 
+    /// This is a method variant of the equivalent associated function on `GenRef`.
+    #[doc = docs_for!(weaken)]
+    fn weaken<N: Mutability>(self) -> GenRef<'s, Min<M, N>, T>;
+
     /// Dereferences the `GenRef`. Same as `Deref::deref(self)`.
     /// This method allows you to call methods on the referenced value explicitly.
     fn deref(&self) -> &T;
@@ -62,6 +102,16 @@ impl<'s, M: Mutability, T: ?Sized> GenRefMethods<'s, M, T> for GenRef<'s, M, T>
         GenRef::as_ptr(self)
     }
 
+    #[inline]
+    fn as_native_ptr(&self) -> M::Ptr<T> {
+        GenRef::as_native_ptr(self)
+    }
+
+    #[inline]
+    fn into_native(self) -> M::Ref<'s, T> {
+        GenRef::into_native(self)
+    }
+
     #[inline]
     fn gen_into_shared_downgrading(self) -> &'s T {
         GenRef::gen_into_shared_downgrading(self)
@@ -78,7 +128,7 @@ impl<'s, M: Mutability, T: ?Sized> GenRefMethods<'s, M, T> for GenRef<'s, M, T>
 
     #[inline]
     fn reborrow(&mut self) -> GenRef<'_, M, T> {
-        GenRef::reborrow(self)
+        Reborrow::reborrow(self)
     }
 
     #[inline]
@@ -90,6 +140,23 @@ impl<'s, M: Mutability, T: ?Sized> GenRefMethods<'s, M, T> for GenRef<'s, M, T>
         GenRef::map(self, f_shared, f_mut)
     }
 
+    #[inline]
+    fn try_map<U: ?Sized>(
+        self,
+        f_mut: impl FnOnce(&mut T) -> Option<&mut U>,
+        f_shared: impl FnOnce(&T) -> Option<&U>,
+    ) -> Option<GenRef<'s, M, U>> {
+        GenRef::try_map(self, f_shared, f_mut)
+    }
+
+    #[inline]
+    fn split_tuple(self) -> T::Split
+    where
+        T: SplitTuple<'s, M>,
+    {
+        GenRef::split_tuple(self)
+    }
+
     #[inline]
     fn map_deref(self) -> GenRef<'s, M, T::Target>
     where
@@ -98,6 +165,11 @@ impl<'s, M: Mutability, T: ?Sized> GenRefMethods<'s, M, T> for GenRef<'s, M, T>
         GenRef::map_deref(self)
     }
 
+    #[inline]
+    fn weaken<N: Mutability>(self) -> GenRef<'s, Min<M, N>, T> {
+        GenRef::weaken(self)
+    }
+
     #[inline]
     fn deref(&self) -> &T {
         self
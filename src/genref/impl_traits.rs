@@ -1,7 +1,11 @@
 use core::cmp::Ordering;
+use core::error::Error;
 use core::fmt;
-use core::hash::Hash;
-use core::iter::{DoubleEndedIterator, FusedIterator, Iterator};
+use core::future::Future;
+use core::hash::{Hash, Hasher};
+use core::iter::{DoubleEndedIterator, Extend, FusedIterator, Iterator};
+use core::pin::Pin;
+use core::task::{Context, Poll};
 
 #[cfg(any(feature = "std", doc))]
 extern crate std;
@@ -9,15 +13,61 @@ extern crate std;
 #[allow(unused_imports)]
 use crate::{GenRef, Mutability, Mutable, Shared};
 
+use super::dispatch::IteratorDispatch;
+#[cfg(any(feature = "std", doc))]
+use super::dispatch::{ReadDispatch, WriteDispatch};
+
 impl<M: Mutability, T: ?Sized> Hash for GenRef<'_, M, T>
 where
     T: Hash,
 {
-    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+    fn hash<H: Hasher>(&self, state: &mut H) {
         Hash::hash(&**self, state)
     }
 }
 
+/// This is only implemented when `M = Mutable`, and is not available for references with generic mutablity.
+impl<T: ?Sized> Hasher for GenRef<'_, Mutable, T>
+where
+    T: Hasher,
+{
+    fn finish(&self) -> u64 {
+        T::finish(&**self)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        T::write(&mut **self, bytes)
+    }
+}
+
+impl<M: Mutability, T: ?Sized, U: ?Sized> AsRef<U> for GenRef<'_, M, T>
+where
+    T: AsRef<U>,
+{
+    fn as_ref(&self) -> &U {
+        T::as_ref(&**self)
+    }
+}
+
+/// This is only implemented when `M = Mutable`, and is not available for references with generic mutablity.
+impl<T: ?Sized, U: ?Sized> AsMut<U> for GenRef<'_, Mutable, T>
+where
+    T: AsMut<U>,
+{
+    fn as_mut(&mut self) -> &mut U {
+        T::as_mut(&mut **self)
+    }
+}
+
+impl<M: Mutability, T: ?Sized> Error for GenRef<'_, M, T>
+where
+    T: Error,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        T::source(&**self)
+    }
+}
+
 macro_rules! impl_fmt_traits {
     ($($trait:ident),+) => {
         $(
@@ -91,6 +141,41 @@ where
     }
 }
 
+#[cfg(any(feature = "std", doc))]
+/// Compares the pointed-to values, like `PartialEq<&U>`.
+///
+/// This is only available with the feature flag `std`.
+impl<M: Mutability, T: ?Sized, U: ?Sized> PartialEq<std::boxed::Box<U>> for GenRef<'_, M, T>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &std::boxed::Box<U>) -> bool {
+        T::eq(&**self, &**other)
+    }
+}
+
+#[cfg(any(feature = "std", doc))]
+/// This is only available with the feature flag `std`.
+impl<M: Mutability, T: ?Sized, U> PartialEq<std::vec::Vec<U>> for GenRef<'_, M, T>
+where
+    T: PartialEq<std::vec::Vec<U>>,
+{
+    fn eq(&self, other: &std::vec::Vec<U>) -> bool {
+        T::eq(&**self, other)
+    }
+}
+
+#[cfg(any(feature = "std", doc))]
+/// This is only available with the feature flag `std`.
+impl<M: Mutability, T: ?Sized> PartialEq<std::string::String> for GenRef<'_, M, T>
+where
+    T: PartialEq<std::string::String>,
+{
+    fn eq(&self, other: &std::string::String) -> bool {
+        T::eq(&**self, other)
+    }
+}
+
 #[cfg(any(feature = "std", doc))]
 /// This is only implemented when `M = Shared`, and is not available for references with generic mutablity.
 ///
@@ -123,22 +208,20 @@ where
     }
 }
 
-/// This is only implemented when `M = Mutable`, and is not available for references with generic mutablity.
-impl<T: ?Sized> Iterator for GenRef<'_, Mutable, T>
-where
-    T: Iterator,
-{
-    type Item = T::Item;
+/// This is implemented when `M = Mutable` and `T: Iterator`, or when `M = Shared` and `&T: Iterator`.
+/// In a generic context, this requires an `M: IteratorDispatch<'s, T>` bound.
+impl<'s, M: IteratorDispatch<'s, T>, T: ?Sized> Iterator for GenRef<'s, M, T> {
+    type Item = M::Item;
     fn next(&mut self) -> Option<Self::Item> {
-        T::next(&mut **self)
+        M::next(self)
     }
 
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
-        T::nth(&mut **self, n)
+        M::nth(self, n)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        T::size_hint(&**self)
+        M::size_hint(self)
     }
 }
 
@@ -165,32 +248,51 @@ where
 /// This is only implemented when `M = Mutable`, and is not available for references with generic mutablity.
 impl<T: ?Sized> FusedIterator for GenRef<'_, Mutable, T> where T: FusedIterator {}
 
-#[cfg(any(feature = "std", doc))]
+/// This is only implemented when `M = Mutable`, and is not available for references with generic mutablity.
+impl<T: ?Sized, A> Extend<A> for GenRef<'_, Mutable, T>
+where
+    T: Extend<A>,
+{
+    fn extend<I: IntoIterator<Item = A>>(&mut self, iter: I) {
+        T::extend(&mut **self, iter)
+    }
+}
+
 /// This is only implemented when `M = Mutable`, and is not available for references with generic mutablity.
 ///
-/// This is only available with the feature flag `std`.
-impl<T: ?Sized> std::io::Write for GenRef<'_, Mutable, T>
+/// Like `&mut T`, this requires `T: Unpin`.
+impl<T: ?Sized> Future for GenRef<'_, Mutable, T>
 where
-    T: std::io::Write,
+    T: Future + Unpin,
 {
+    type Output = T::Output;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        T::poll(Pin::new(&mut **self), cx)
+    }
+}
+
+#[cfg(any(feature = "std", doc))]
+/// This is implemented when `M = Mutable` and `T: Write`, or when `M = Shared` and `&T: Write` (for example `File` or `TcpStream`).
+/// In a generic context, this requires an `M: WriteDispatch<'s, T>` bound.
+///
+/// This is only available with the feature flag `std`.
+impl<'s, M: WriteDispatch<'s, T>, T: ?Sized> std::io::Write for GenRef<'s, M, T> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        T::write(&mut **self, buf)
+        M::write(self, buf)
     }
     fn flush(&mut self) -> std::io::Result<()> {
-        T::flush(&mut **self)
+        M::flush(self)
     }
 }
 
 #[cfg(any(feature = "std", doc))]
-/// This is only implemented when `M = Mutable`, and is not available for references with generic mutablity.
+/// This is implemented when `M = Mutable` and `T: Read`, or when `M = Shared` and `&T: Read` (for example `File` or `TcpStream`).
+/// In a generic context, this requires an `M: ReadDispatch<'s, T>` bound.
 ///
 /// This is only available with the feature flag `std`.
-impl<T: ?Sized> std::io::Read for GenRef<'_, Mutable, T>
-where
-    T: std::io::Read,
-{
+impl<'s, M: ReadDispatch<'s, T>, T: ?Sized> std::io::Read for GenRef<'s, M, T> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        T::read(&mut **self, buf)
+        M::read(self, buf)
     }
 }
 
@@ -17,11 +17,37 @@
 //! ```
 //!
 //! The main items of this crate are the `GenRef` struct, which represents a safe reference (like `&` and `&mut`) that is generic over mutability; and the `Mutability` trait, which is used as a bound on *generic mutability parameters*.
+//!
+//! With the feature flag `macros`, derive and attribute macros that generate generically mutable accessors for user types (like `GenFields` and `gen_mut_pair`) are also available.
 
+mod downgrade;
+mod gen_dyn;
+mod gen_either;
 mod genref;
 mod macros;
 mod mutability;
+mod reborrow;
 
+pub use downgrade::Downgrade;
+pub use gen_dyn::GenDyn;
+pub use gen_either::GenEither;
+#[cfg(feature = "macros")]
+pub use generic_mutability_macros::{
+    extension_trait, gen_dyn, gen_fn, gen_mut_pair, gen_trait, generic_mutability, Downgrade,
+    GenFields, GenView, GenVisit, Reborrow,
+};
+pub use genref::dispatch::IteratorDispatch;
+#[cfg(any(feature = "std", doc))]
+pub use genref::dispatch::{ReadDispatch, WriteDispatch};
 pub use genref::genref_methods::GenRefMethods;
-pub use genref::GenRef;
-pub use mutability::{IsMutable, IsShared, Mutability, MutabilityEnum, Mutable, Shared};
+pub use genref::{GenRef, SplitTuple};
+#[doc(hidden)]
+pub use macros::{__GenBinding, __mutability_of};
+#[cfg(any(feature = "std", doc))]
+pub use mutability::VecFamily;
+pub use mutability::{
+    BoolMutability, BoolToMutability, GenRefFamily, IsMutable, IsShared, Max, Min, Mutability,
+    MutabilityEnum, MutabilityFamily, MutabilityMax, MutabilityMin, MutabilityOf, Mutable,
+    OptionFamily, RefFamily, Shared,
+};
+pub use reborrow::Reborrow;
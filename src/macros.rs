@@ -1,3 +1,5 @@
+use crate::{GenRef, IsMutable, IsShared, Mutability, MutabilityEnum};
+
 /// This macro simplifies the implementation of generically mutable APIs, where the mutable and the shared code paths are (mostly) identical.
 /// It has the following syntax:
 ///
@@ -7,7 +9,7 @@
 /// }}
 /// ```
 /// where `$M` is the name of the generic parameter you want to unwrap.
-/// The code inside has access to three macros, which allow it to emit different code depending on the value of `M`:
+/// The code inside has access to the following macros, which allow it to emit different code depending on the value of `M`:
 ///
 /// - `from_gen!($genref)` / `from_gen!()`
 ///
@@ -16,11 +18,11 @@
 ///     The return value can be converted back into a `GenRef` using the `into_gen!` macro.
 ///     If no arguments are passed, it returns a closure `Fn(GenRef<'_, M, T>) -> &T` / `Fn(GenRef<'_, M, T>) -> &mut T`.
 ///
-/// - `into_mut!($reference)` / `into_mut!(&gen $place)` / `into_mut!()`
+/// - `into_gen!($reference)` / `into_gen!(&gen $place)` / `into_gen!()`
 ///
 ///     Calls `GenRef::gen_from_shared` and `GenRef::gen_from_mut` on the reference passed as an argument, and returns the resulting `GenRef`.
-///     The type of the input is different in the shared vs the mutable case, so it is not possible to call this with a reference that was not created via `from_gen!` or `switch_mut_shared!`.
-///     To allow accessing fields, you can use the `into_mut!(&gen $place)` syntax, which references the `$place` expression with the appropriate kind of reference.
+///     The type of the input is different in the shared vs the mutable case, so it is not possible to call this with a reference that was not created via `from_gen!` or `switch_shared_mut!`.
+///     To allow accessing fields, you can use the `into_gen!(&gen $place)` syntax, which references the `$place` expression with the appropriate kind of reference.
 ///     If no arguments are passed, it returns a closure `Fn(&T) -> GenRef<'_, M, T>` / `Fn(&mut T) -> GenRef<'_, M, T>`.
 ///
 /// - `switch_shared_mut!($shared_expr, $mutable_expr)` / `switch_shared_mut!({ $shared_tts } { $mutable_tts })`
@@ -29,16 +31,85 @@
 ///     The `switch_shared_mut!({ $shared_tts } { $mutable_tts })` syntax allows you to expand to arbitrary token trees, not just expressions.
 ///     This requires you to wrap them in brackets, which will not appear in the expansion.
 ///     Also note that in this syntax there is no comma separating the two cases.
+///     When invoked in statement position, the token trees may also contain statements and items, such as `use` declarations, local types or `impl` blocks, which are then only visible in the corresponding case.
+///
+/// - `gen_either!($value)`
+///
+///     Wraps `$value` into a `GenEither<M, S, Mu>`, where `$value` is of type `S` in the shared case and of type `Mu` in the mutable case.
+///     Unlike the other values created inside `gen_mut!`, the result has the same type in both cases, so it can be moved out of the macro call.
+///     This is useful for returning iterators, guards or other values whose types differ by mutability.
+///
+/// - `from_either!($either)`
+///
+///     Unwraps a `GenEither<M, S, Mu>` into `S` in the shared case and into `Mu` in the mutable case.
+///
+/// # Multiple mutability parameters
+///
+/// ```rust, ignore
+/// gen_mut!{ $M1, $M2, ... => {
+///     /* code */
+/// }}
+/// ```
+///
+/// When more than one mutability parameter is listed (they must be identifiers), the code is expanded once for every combination of their values.
+/// The same macros are available, but each invocation has to name the mutability parameter it refers to, followed by a colon:
+/// `from_gen!($M1: $genref)`, `into_gen!($M2: &gen $place)`, `switch_shared_mut!($M1: $shared_expr, $mutable_expr)`, and so on.
+///
+/// ```rust
+/// # use generic_mutability::{gen_mut, GenRef, Mutability};
+/// fn gen_both<'a, M1: Mutability, M2: Mutability>(
+///     first: GenRef<'a, M1, Vec<i32>>,
+///     second: GenRef<'a, M2, Vec<i32>>,
+/// ) -> (GenRef<'a, M1, i32>, GenRef<'a, M2, i32>) {
+///     gen_mut!{ M1, M2 => {
+///         let first = from_gen!(M1: first);
+///         let second = from_gen!(M2: second);
+///         (into_gen!(M1: &gen first[0]), into_gen!(M2: &gen second[0]))
+///     }}
+/// }
+/// ```
+///
+/// # Compile errors
+///
+/// The value returned by `from_gen!` has a different type in the two cases, so it can not leave the macro call:
+///
+/// ```compile_fail
+/// # use generic_mutability::{gen_mut, GenRef, Mutability};
+/// fn escape<M: Mutability>(genref: GenRef<'_, M, i32>) -> &mut i32 {
+///     gen_mut!{ M => {
+///         from_gen!(genref)
+///     }}
+/// }
+/// ```
+///
+/// With multiple mutability parameters, the sub-macros only accept the parameters listed in the `gen_mut!` call:
+///
+/// ```compile_fail
+/// # use generic_mutability::{gen_mut, GenRef, Mutability};
+/// fn unlisted<M1: Mutability, M2: Mutability, M3: Mutability>(genref: GenRef<'_, M3, i32>) {
+///     gen_mut!{ M1, M2 => {
+///         let _ = from_gen!(M3: genref);
+///     }}
+/// }
+/// ```
 
+#[allow(
+    clippy::doc_overindented_list_items,
+    clippy::empty_line_after_doc_comments
+)]
 #[macro_export]
 macro_rules! gen_mut {
+    ($m1:ident, $($m:ident),+ => $code:expr) => {
+        // The `$` token is passed along so that the helper can define macros with metavariables and repetitions.
+        $crate::__gen_mut_multi!(($) [] $m1, $($m),+ => $code)
+    };
     ($m:ty => $code:expr) => {
         match <$m as $crate::Mutability>::mutability() {
             $crate::MutabilityEnum::Shared(_proof) => {
                 #[allow(unused_macros)]
                 macro_rules! into_gen {
                     () => {
-                        |genref| $crate::GenRef::gen_from_shared(genref, _proof)
+                        move |genref| $crate::GenRef::gen_from_shared(genref, _proof)
                     };
                     (&gen $genref:expr) => {
                         $crate::GenRef::gen_from_shared(&$genref, _proof)
@@ -50,7 +121,7 @@ macro_rules! gen_mut {
                 #[allow(unused_macros)]
                 macro_rules! from_gen {
                     () => {
-                        |genref| $crate::GenRef::gen_into_shared(genref, _proof)
+                        move |genref| $crate::GenRef::gen_into_shared(genref, _proof)
                     };
                     ($reference:expr) => {
                         $crate::GenRef::gen_into_shared($reference, _proof)
@@ -62,19 +133,31 @@ macro_rules! gen_mut {
                         // For syntactic reasons, it is impossible to define a macro with a repeating capture group inside another macro.
                         // (The definition would be interpreted as a repeating expansion group of the outer macro.)
                         // So, processing is outsourced to a macro defined elsewhere.
-                        $crate::__unwrap($shared);
+                        $crate::__unwrap!($shared)
                     };
                     ($shared:expr, $mutable:expr) => {
                         $shared
                     };
                 }
+                #[allow(unused_macros)]
+                macro_rules! gen_either {
+                    ($value:expr) => {
+                        $crate::GenEither::new_shared($value, _proof)
+                    };
+                }
+                #[allow(unused_macros)]
+                macro_rules! from_either {
+                    ($either:expr) => {
+                        $crate::GenEither::into_shared($either, _proof)
+                    };
+                }
                 $code
             }
             $crate::MutabilityEnum::Mutable(_proof) => {
                 #[allow(unused_macros)]
                 macro_rules! into_gen {
                     () => {
-                        |genref| $crate::GenRef::gen_from_mut(genref, _proof)
+                        move |genref| $crate::GenRef::gen_from_mut(genref, _proof)
                     };
                     (&gen $genref:expr) => {
                         $crate::GenRef::gen_from_mut(&mut $genref, _proof)
@@ -86,7 +169,7 @@ macro_rules! gen_mut {
                 #[allow(unused_macros)]
                 macro_rules! from_gen {
                     () => {
-                        |genref| $crate::GenRef::gen_into_mut(genref, _proof)
+                        move |genref| $crate::GenRef::gen_into_mut(genref, _proof)
                     };
                     ($reference:expr) => {
                         $crate::GenRef::gen_into_mut($reference, _proof)
@@ -104,12 +187,147 @@ macro_rules! gen_mut {
                         $mutable
                     };
                 }
+                #[allow(unused_macros)]
+                macro_rules! gen_either {
+                    ($value:expr) => {
+                        $crate::GenEither::new_mut($value, _proof)
+                    };
+                }
+                #[allow(unused_macros)]
+                macro_rules! from_either {
+                    ($either:expr) => {
+                        $crate::GenEither::into_mut($either, _proof)
+                    };
+                }
                 $code
             }
         }
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __gen_mut_multi {
+    (($d:tt) [$($done:tt)*] $m:ident $(, $rest:ident)* => $code:expr) => {
+        match <$m as $crate::Mutability>::mutability() {
+            $crate::MutabilityEnum::Shared(_proof) => {
+                $crate::__gen_mut_multi!(($d) [$($done)* ($m shared _proof)] $($rest),* => $code)
+            }
+            $crate::MutabilityEnum::Mutable(_proof) => {
+                $crate::__gen_mut_multi!(($d) [$($done)* ($m mutable _proof)] $($rest),* => $code)
+            }
+        }
+    };
+    (($d:tt) [$(($m:ident $kind:ident $proof:ident))*] => $code:expr) => {{
+        #[allow(unused_macros)]
+        macro_rules! into_gen {
+            $(
+                ($m:) => {
+                    $crate::__gen_mut_op!($kind into_gen $proof)
+                };
+                ($m: &gen $d place:expr) => {
+                    $crate::__gen_mut_op!($kind into_gen_place $proof $d place)
+                };
+                ($m: $d reference:expr) => {
+                    $crate::__gen_mut_op!($kind into_gen $proof $d reference)
+                };
+            )*
+        }
+        #[allow(unused_macros)]
+        macro_rules! from_gen {
+            $(
+                ($m:) => {
+                    $crate::__gen_mut_op!($kind from_gen $proof)
+                };
+                ($m: $d genref:expr) => {
+                    $crate::__gen_mut_op!($kind from_gen $proof $d genref)
+                };
+            )*
+        }
+        #[allow(unused_macros)]
+        macro_rules! switch_shared_mut {
+            $(
+                ($m: $d shared:tt $d mutable:tt) => {
+                    $crate::__gen_mut_op!($kind switch { $d shared } { $d mutable })
+                };
+                ($m: $d shared:expr, $d mutable:expr) => {
+                    $crate::__gen_mut_op!($kind switch { $d shared } { $d mutable })
+                };
+            )*
+        }
+        #[allow(unused_macros)]
+        macro_rules! gen_either {
+            $(
+                ($m: $d value:expr) => {
+                    $crate::__gen_mut_op!($kind gen_either $proof $d value)
+                };
+            )*
+        }
+        #[allow(unused_macros)]
+        macro_rules! from_either {
+            $(
+                ($m: $d either:expr) => {
+                    $crate::__gen_mut_op!($kind from_either $proof $d either)
+                };
+            )*
+        }
+        $code
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __gen_mut_op {
+    (shared into_gen $proof:ident) => {
+        move |genref| $crate::GenRef::gen_from_shared(genref, $proof)
+    };
+    (shared into_gen_place $proof:ident $place:expr) => {
+        $crate::GenRef::gen_from_shared(&$place, $proof)
+    };
+    (shared into_gen $proof:ident $reference:expr) => {
+        $crate::GenRef::gen_from_shared($reference, $proof)
+    };
+    (shared from_gen $proof:ident) => {
+        move |genref| $crate::GenRef::gen_into_shared(genref, $proof)
+    };
+    (shared from_gen $proof:ident $genref:expr) => {
+        $crate::GenRef::gen_into_shared($genref, $proof)
+    };
+    (shared switch $shared:tt $mutable:tt) => {
+        $crate::__unwrap!($shared)
+    };
+    (shared gen_either $proof:ident $value:expr) => {
+        $crate::GenEither::new_shared($value, $proof)
+    };
+    (shared from_either $proof:ident $either:expr) => {
+        $crate::GenEither::into_shared($either, $proof)
+    };
+    (mutable into_gen $proof:ident) => {
+        move |genref| $crate::GenRef::gen_from_mut(genref, $proof)
+    };
+    (mutable into_gen_place $proof:ident $place:expr) => {
+        $crate::GenRef::gen_from_mut(&mut $place, $proof)
+    };
+    (mutable into_gen $proof:ident $reference:expr) => {
+        $crate::GenRef::gen_from_mut($reference, $proof)
+    };
+    (mutable from_gen $proof:ident) => {
+        move |genref| $crate::GenRef::gen_into_mut(genref, $proof)
+    };
+    (mutable from_gen $proof:ident $genref:expr) => {
+        $crate::GenRef::gen_into_mut($genref, $proof)
+    };
+    (mutable switch $shared:tt $mutable:tt) => {
+        $crate::__unwrap!($mutable)
+    };
+    (mutable gen_either $proof:ident $value:expr) => {
+        $crate::GenEither::new_mut($value, $proof)
+    };
+    (mutable from_either $proof:ident $either:expr) => {
+        $crate::GenEither::into_mut($either, $proof)
+    };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __unwrap{
@@ -134,11 +352,415 @@ macro_rules! __unwrap{
 /// field!(&gen {obtain_genref()}.field)
 /// field!(&gen {container.genref}.field)
 /// ```
+///
+/// Like ordinary field access, fields are accessed through `Box` and other smart pointers implicitly.
+/// The `.*` segment explicitly dereferences the current value (`field!(&gen genref.boxed.*)`), which requires `Deref` and `DerefMut`.
+///
+/// # Optional projections
+///
+/// The following segments may fail, and turn the result of the macro into an `Option<GenRef<'_, M, _>>`.
+/// Segments after a failing one are only evaluated if it succeeds, similar to `?` in a function returning `Option`.
+///
+/// - `?` goes through an `Option` field, using `GenRef::transpose`: `field!(&gen genref.parent?.name)`.
+/// - `as $Enum::$Variant.$field` projects into a field of an enum variant, and returns `None` if the value is a different variant: `field!(&gen shape as Shape::Circle.radius)`.
+///     The field of a tuple variant is selected by its index: `field!(&gen value as Value::Number.0)`.
+///
+/// ```rust
+/// # use generic_mutability::{field, GenRef, Mutability};
+/// enum Shape {
+///     Circle { radius: f64 },
+///     Square(f64),
+/// }
+/// struct Node {
+///     shape: Shape,
+///     child: Option<Box<Node>>,
+/// }
+///
+/// fn gen_child_radius<M: Mutability>(node: GenRef<'_, M, Node>) -> Option<GenRef<'_, M, f64>> {
+///     field!(&gen node.child?.shape as Shape::Circle.radius)
+/// }
+///
+/// let mut node = Node {
+///     shape: Shape::Square(1.0),
+///     child: Some(Box::new(Node { shape: Shape::Circle { radius: 2.0 }, child: None })),
+/// };
+/// *gen_child_radius(GenRef::from(&mut node)).unwrap() = 3.0;
+/// assert_eq!(gen_child_radius(GenRef::from(&node)).unwrap(), &3.0);
+///
+/// let square = GenRef::from(&node.shape);
+/// assert_eq!(field!(&gen square as Shape::Square.0).unwrap(), &1.0);
+/// assert!(field!(&gen square as Shape::Circle.radius).is_none());
+/// ```
+#[allow(clippy::doc_overindented_list_items)]
 #[macro_export]
 macro_rules! field {
-    (&gen $genref:tt $($field:tt)+) => {
+    (&gen $genref:tt $($path:tt)+) => {
+        {
+            #[allow(unused_braces)]
+            #[allow(unused_parens)]
+            let projected = $crate::__field!(@munch plain ($genref) [] $($path)+);
+            projected
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __field {
+    // Plain segments (field access, indexing and dereferencing) are collected and applied in a single `GenRef::map`.
+    (@munch $mode:ident ($($current:tt)*) [$($ops:tt)*] . * $($rest:tt)*) => {
+        $crate::__field!(@munch $mode ($($current)*) [$($ops)* {*}] $($rest)*)
+    };
+    (@munch $mode:ident ($($current:tt)*) [$($ops:tt)*] . $field:tt $($rest:tt)*) => {
+        $crate::__field!(@munch $mode ($($current)*) [$($ops)* {. $field}] $($rest)*)
+    };
+    (@munch $mode:ident ($($current:tt)*) [$($ops:tt)*] [$($index:tt)*] $($rest:tt)*) => {
+        $crate::__field!(@munch $mode ($($current)*) [$($ops)* {[$($index)*]}] $($rest)*)
+    };
+    // Failing segments switch to the `option` mode, in which the current value is an `Option<GenRef<'_, M, _>>`.
+    (@munch plain ($($current:tt)*) [$($ops:tt)*] ? $($rest:tt)*) => {
+        $crate::__field!(@munch option (
+            $crate::GenRef::transpose($crate::__field!(@flush plain ($($current)*) [$($ops)*]))
+        ) [] $($rest)*)
+    };
+    (@munch option ($($current:tt)*) [$($ops:tt)*] ? $($rest:tt)*) => {
+        $crate::__field!(@munch option (
+            $crate::__field!(@flush option ($($current)*) [$($ops)*]).and_then($crate::GenRef::transpose)
+        ) [] $($rest)*)
+    };
+    (@munch plain ($($current:tt)*) [$($ops:tt)*] as $($variant:ident)::+ . $field:tt $($rest:tt)*) => {
+        $crate::__field!(@munch option (
+            $crate::__field!(@variant ($crate::__field!(@flush plain ($($current)*) [$($ops)*])) $($variant)::+ . $field)
+        ) [] $($rest)*)
+    };
+    (@munch option ($($current:tt)*) [$($ops:tt)*] as $($variant:ident)::+ . $field:tt $($rest:tt)*) => {
+        $crate::__field!(@munch option (
+            $crate::__field!(@flush option ($($current)*) [$($ops)*])
+                .and_then(|genref| $crate::__field!(@variant (genref) $($variant)::+ . $field))
+        ) [] $($rest)*)
+    };
+    (@munch $mode:ident ($($current:tt)*) [$($ops:tt)*]) => {
+        $crate::__field!(@flush $mode ($($current)*) [$($ops)*])
+    };
+
+    (@flush $mode:ident ($($current:tt)*) []) => {
+        $($current)*
+    };
+    (@flush plain ($($current:tt)*) [$($ops:tt)+]) => {
+        $crate::GenRef::map(
+            $($current)*,
+            |r| &$crate::__field!(@place ((*r)) $($ops)+),
+            |r| &mut $crate::__field!(@place ((*r)) $($ops)+),
+        )
+    };
+    (@flush option ($($current:tt)*) [$($ops:tt)+]) => {
+        ($($current)*).map(|genref| $crate::__field!(@flush plain (genref) [$($ops)+]))
+    };
+
+    (@variant ($($current:tt)*) $($variant:ident)::+ . $field:tt) => {
+        $crate::GenRef::try_map(
+            $($current)*,
+            |r| match r {
+                $($variant)::+ { $field: field, .. } => Some(field),
+                #[allow(unreachable_patterns)]
+                _ => None,
+            },
+            |r| match r {
+                $($variant)::+ { $field: field, .. } => Some(field),
+                #[allow(unreachable_patterns)]
+                _ => None,
+            },
+        )
+    };
+
+    (@place ($($place:tt)*)) => {
+        $($place)*
+    };
+    (@place ($($place:tt)*) {*} $($ops:tt)*) => {
+        $crate::__field!(@place ((*$($place)*)) $($ops)*)
+    };
+    (@place ($($place:tt)*) {$($op:tt)*} $($ops:tt)*) => {
+        $crate::__field!(@place ($($place)* $($op)*) $($ops)*)
+    };
+}
+
+/// Splits a `GenRef` into multiple `GenRef`s to disjoint fields.
+///
+/// Returns a tuple with a `GenRef` to each listed field, in order.
+/// The fields are specified as paths relative to the referenced value, using field access and indexing (`a`, `b.c`, `d[0]`, `0.1`).
+///
+/// In the mutable case the fields are borrowed simultaneously, so the borrow checker verifies that they do not overlap, just like with `(&mut x.a, &mut x.b)`.
+/// Note that indexing borrows the whole indexed value, so `d[0]` and `d[1]` can not be listed together.
+///
+/// The receiver (the expression returning `GenRef`) must be a single token (an identifier) or it must be wrapped in braces or parens.
+///
+/// ```rust
+/// # use generic_mutability::{fields, GenRef, Mutability};
+/// struct Foo {
+///     a: i32,
+///     b: (i32, i32),
+///     c: [i32; 2],
+/// }
+///
+/// fn gen_parts<M: Mutability>(
+///     foo: GenRef<'_, M, Foo>,
+/// ) -> (GenRef<'_, M, i32>, GenRef<'_, M, i32>, GenRef<'_, M, i32>) {
+///     fields!(&gen foo => a, b.1, c[0])
+/// }
+///
+/// let mut foo = Foo { a: 1, b: (2, 3), c: [4, 5] };
+/// let (mut a, mut b1, c0) = gen_parts(GenRef::from(&mut foo));
+/// *a += *c0;
+/// *b1 += *c0;
+/// assert_eq!((foo.a, foo.b.1), (5, 7));
+/// ```
+///
+/// Overlapping fields are rejected:
+///
+/// ```compile_fail
+/// # use generic_mutability::{fields, GenRef, Mutability};
+/// fn gen_overlapping<M: Mutability>(
+///     genref: GenRef<'_, M, ((i32, i32), i32)>,
+/// ) -> (GenRef<'_, M, (i32, i32)>, GenRef<'_, M, i32>) {
+///     fields!(&gen genref => 0, 0.1)
+/// }
+/// ```
+#[macro_export]
+macro_rules! fields {
+    (&gen $genref:tt => $($paths:tt)+) => {
+        $crate::__fields!(@split ($genref) [] [] $($paths)+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fields {
+    (@split $genref:tt [$($done:tt)*] [$($current:tt)+] , $($rest:tt)*) => {
+        $crate::__fields!(@split $genref [$($done)* {$($current)+}] [] $($rest)*)
+    };
+    (@split $genref:tt [$($done:tt)*] [$($current:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::__fields!(@split $genref [$($done)*] [$($current)* $token] $($rest)*)
+    };
+    (@split $genref:tt [$($done:tt)*] [$($current:tt)+]) => {
+        $crate::__fields!(@emit $genref [$($done)* {$($current)+}])
+    };
+    (@split $genref:tt [$($done:tt)*] []) => {
+        $crate::__fields!(@emit $genref [$($done)*])
+    };
+    (@emit ($genref:tt) [$({$($path:tt)+})+]) => {{
         #[allow(unused_braces)]
         #[allow(unused_parens)]
-        $crate::GenRef::map($genref, |r| & r $($field)+, |r| &mut r $($field)+)
+        let genref = $genref;
+        match $crate::__mutability_of(&genref) {
+            $crate::MutabilityEnum::Shared(proof) => {
+                let r = $crate::GenRef::gen_into_shared(genref, proof);
+                ($($crate::GenRef::gen_from_shared(&(*r).$($path)+, proof),)+)
+            }
+            $crate::MutabilityEnum::Mutable(proof) => {
+                let r = $crate::GenRef::gen_into_mut(genref, proof);
+                ($($crate::GenRef::gen_from_mut(&mut (*r).$($path)+, proof),)+)
+            }
+        }
+    }};
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn __mutability_of<M: Mutability, T: ?Sized>(_genref: &GenRef<'_, M, T>) -> MutabilityEnum<M> {
+    M::mutability()
+}
+
+/// Matches on the value behind a `GenRef`, binding every variable of the patterns as a `GenRef`.
+///
+/// This is the generic equivalent of matching on a reference with match ergonomics, where bindings become `&T` or `&mut T`.
+///
+/// ```rust, ignore
+/// gen_match!($genref, {
+///     $pattern $(if $guard)? => $body,
+///     ...
+/// })
+/// ```
+///
+/// The patterns are matched against `&T` / `&mut T`, so every binding of type `U` becomes a `GenRef<'_, M, U>` in the arm body.
+/// Bindings inside slice patterns (`[first, rest @ ..]`) and or-patterns are also supported.
+/// The arm bodies are expanded once for each mutability, like the code inside `gen_mut!`, but they only see the `GenRef` bindings, so usually no further macros are needed.
+///
+/// The guards are evaluated before the bindings are converted, so they see `&U` / `&mut U` instead of `GenRef`s.
+///
+/// As macros can not tell bindings and constants apart, unit variants and constants have to be written as paths (`Self::Empty`, `Enum::Unit`, `i32::MAX`), except for `None`.
+/// `ref` and `mut` bindings are not supported.
+///
+/// ```rust
+/// # use generic_mutability::{gen_match, GenRef, Mutability};
+/// enum Expr {
+///     Number(f64),
+///     Negate(Box<Expr>),
+///     Sum { terms: Vec<Expr>, scale: f64 },
+///     Empty,
+/// }
+///
+/// fn gen_first_number<M: Mutability>(expr: GenRef<'_, M, Expr>) -> Option<GenRef<'_, M, f64>> {
+///     gen_match!(expr, {
+///         Expr::Number(value) => Some(value),
+///         Expr::Negate(inner) => gen_first_number(GenRef::map_deref(inner)),
+///         Expr::Sum { terms, .. } => gen_match!(GenRef::map_deref(terms), {
+///             [first, ..] => gen_first_number(first),
+///             [] => None,
+///         }),
+///         Expr::Empty => None,
+///     })
+/// }
+///
+/// let mut expr = Expr::Negate(Box::new(Expr::Sum { terms: vec![Expr::Number(1.0)], scale: 1.0 }));
+/// *gen_first_number(GenRef::from(&mut expr)).unwrap() = 2.0;
+/// assert_eq!(gen_first_number(GenRef::from(&expr)).unwrap(), &2.0);
+/// ```
+#[macro_export]
+macro_rules! gen_match {
+    ($genref:expr, { $($arms:tt)* }) => {
+        $crate::__gen_match!(@arms ($genref) [] $($arms)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __gen_match {
+    // Splitting the arms into patterns, guards and bodies.
+    (@arms $genref:tt [$($done:tt)*]) => {
+        $crate::__gen_match!(@emit $genref [$($done)*])
+    };
+    (@arms $genref:tt [$($done:tt)*] $($rest:tt)+) => {
+        $crate::__gen_match!(@pattern $genref [$($done)*] [] $($rest)+)
+    };
+    (@pattern $genref:tt [$($done:tt)*] [$($pattern:tt)*] => $($rest:tt)*) => {
+        $crate::__gen_match!(@body $genref [$($done)*] [$($pattern)*] [] $($rest)*)
+    };
+    (@pattern $genref:tt [$($done:tt)*] [$($pattern:tt)*] if $($rest:tt)*) => {
+        $crate::__gen_match!(@guard $genref [$($done)*] [$($pattern)*] [] $($rest)*)
+    };
+    (@pattern $genref:tt [$($done:tt)*] [$($pattern:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::__gen_match!(@pattern $genref [$($done)*] [$($pattern)* $token] $($rest)*)
+    };
+    (@guard $genref:tt [$($done:tt)*] [$($pattern:tt)*] [$($guard:tt)*] => $($rest:tt)*) => {
+        $crate::__gen_match!(@body $genref [$($done)*] [$($pattern)*] [if $($guard)*] $($rest)*)
+    };
+    (@guard $genref:tt [$($done:tt)*] [$($pattern:tt)*] [$($guard:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::__gen_match!(@guard $genref [$($done)*] [$($pattern)*] [$($guard)* $token] $($rest)*)
+    };
+    (@body $genref:tt [$($done:tt)*] $pattern:tt $guard:tt { $($body:tt)* } , $($rest:tt)*) => {
+        $crate::__gen_match!(@arms $genref [$($done)* ($pattern $guard { $($body)* })] $($rest)*)
+    };
+    (@body $genref:tt [$($done:tt)*] $pattern:tt $guard:tt { $($body:tt)* } $($rest:tt)*) => {
+        $crate::__gen_match!(@arms $genref [$($done)* ($pattern $guard { $($body)* })] $($rest)*)
+    };
+    (@body $genref:tt [$($done:tt)*] $pattern:tt $guard:tt $($rest:tt)*) => {
+        $crate::__gen_match!(@expr $genref [$($done)*] $pattern $guard [] $($rest)*)
+    };
+    (@expr $genref:tt [$($done:tt)*] $pattern:tt $guard:tt [$($body:tt)*] , $($rest:tt)*) => {
+        $crate::__gen_match!(@arms $genref [$($done)* ($pattern $guard { $($body)* })] $($rest)*)
+    };
+    (@expr $genref:tt [$($done:tt)*] $pattern:tt $guard:tt [$($body:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::__gen_match!(@expr $genref [$($done)*] $pattern $guard [$($body)* $token] $($rest)*)
+    };
+    (@expr $genref:tt [$($done:tt)*] $pattern:tt $guard:tt [$($body:tt)*]) => {
+        $crate::__gen_match!(@arms $genref [$($done)* ($pattern $guard { $($body)* })])
+    };
+
+    (@emit ($genref:expr) [$(([$($pattern:tt)*] [$($guard:tt)*] $body:tt))*]) => {{
+        let genref = $genref;
+        match $crate::__mutability_of(&genref) {
+            $crate::MutabilityEnum::Shared(proof) => match $crate::GenRef::gen_into_shared(genref, proof) {
+                $($($pattern)* $($guard)* => {
+                    $crate::__gen_match!(@bind proof $($pattern)*);
+                    $body
+                })*
+            },
+            $crate::MutabilityEnum::Mutable(proof) => match $crate::GenRef::gen_into_mut(genref, proof) {
+                $($($pattern)* $($guard)* => {
+                    $crate::__gen_match!(@bind proof $($pattern)*);
+                    $body
+                })*
+            },
+        }
+    }};
+
+    // Collecting the bindings of a pattern, and converting each of them into a `GenRef`.
+    (@bind $proof:ident) => {};
+    (@bind $proof:ident ref $($rest:tt)*) => {
+        ::core::compile_error!("`ref` bindings are not supported in `gen_match!`, every binding is a `GenRef`")
+    };
+    (@bind $proof:ident mut $($rest:tt)*) => {
+        ::core::compile_error!("`mut` bindings are not supported in `gen_match!`, every binding is a `GenRef`")
+    };
+    (@bind $proof:ident None $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($rest)*)
     };
+    (@bind $proof:ident $literal:literal $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($rest)*)
+    };
+    (@bind $proof:ident :: $segment:ident $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($rest)*)
+    };
+    (@bind $proof:ident $segment:ident :: $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof :: $($rest)*)
+    };
+    (@bind $proof:ident $field:ident : $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($rest)*)
+    };
+    (@bind $proof:ident $name:ident ! $arguments:tt $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($rest)*)
+    };
+    (@bind $proof:ident $path:ident ($($inner:tt)*) $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($inner)* $($rest)*)
+    };
+    (@bind $proof:ident $path:ident {$($inner:tt)*} $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($inner)* $($rest)*)
+    };
+    (@bind $proof:ident $binding:ident $($rest:tt)*) => {
+        // Or-patterns bind the same name multiple times, so the conversion has to accept `GenRef`s as well.
+        let $binding = $crate::__GenBinding::__gen_binding($binding, $proof);
+        $crate::__gen_match!(@bind $proof $($rest)*)
+    };
+    (@bind $proof:ident ($($inner:tt)*) $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($inner)* $($rest)*)
+    };
+    (@bind $proof:ident [$($inner:tt)*] $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($inner)* $($rest)*)
+    };
+    (@bind $proof:ident {$($inner:tt)*} $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($inner)* $($rest)*)
+    };
+    (@bind $proof:ident $token:tt $($rest:tt)*) => {
+        $crate::__gen_match!(@bind $proof $($rest)*)
+    };
+}
+
+#[doc(hidden)]
+pub trait __GenBinding<'s, M: Mutability, P> {
+    type Target: ?Sized;
+    fn __gen_binding(self, proof: P) -> GenRef<'s, M, Self::Target>;
+}
+
+impl<'s, M: Mutability, T: ?Sized> __GenBinding<'s, M, IsShared<M>> for &'s T {
+    type Target = T;
+    #[inline]
+    fn __gen_binding(self, proof: IsShared<M>) -> GenRef<'s, M, T> {
+        GenRef::gen_from_shared(self, proof)
+    }
+}
+
+impl<'s, M: Mutability, T: ?Sized> __GenBinding<'s, M, IsMutable<M>> for &'s mut T {
+    type Target = T;
+    #[inline]
+    fn __gen_binding(self, proof: IsMutable<M>) -> GenRef<'s, M, T> {
+        GenRef::gen_from_mut(self, proof)
+    }
+}
+
+impl<'s, M: Mutability, T: ?Sized, P> __GenBinding<'s, M, P> for GenRef<'s, M, T> {
+    type Target = T;
+    #[inline]
+    fn __gen_binding(self, _proof: P) -> GenRef<'s, M, T> {
+        self
+    }
 }
@@ -1,4 +1,10 @@
 use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop};
+
+use crate::GenRef;
+
+#[cfg(any(feature = "std", doc))]
+extern crate std;
 
 mod seal {
     use crate::{Mutable, Shared};
@@ -16,13 +22,101 @@ mod seal {
 ///
 /// Note that while mutability parameters are implemented as type parameters, they represent an entirely different kind of generic parameter.
 /// For this reason, the `M: Mutability` bound should be applied even in struct definitions where bounds are generally discouraged.
+///
+/// Both implementors are `'static`, which is reflected in the supertraits, so values carrying a mutability parameter (such as proofs captured by closures) do not need an extra `M: 'a` bound.
 #[allow(clippy::missing_safety_doc)]
 // SAFETY: this trait must only be implemented for `Shared` and `Mutable`. `Shared::mutability()` must return `MutabilityEnum::Shared(IsShared<Shared>)` and `Mutable::mutability()` must return `MutabilityEnum::Mutable(IsMutable<Mutable>)`.
-pub unsafe trait Mutability: Copy + Sized + seal::Sealed {
+// The same applies to `MUTABILITY`, and `IS_MUTABLE` must be `true` only for `Mutable`.
+// `Min` and `Max` must be the minimum and maximum of `Self` and `N`, where `Shared` is less than `Mutable`.
+// `Ref` and `Ptr` must be the native reference and pointer types with the mutability of `Self`.
+// `Select<S, Mu>` must be `S` for `Shared` and `Mu` for `Mutable`.
+pub unsafe trait Mutability: Copy + Sized + 'static + seal::Sealed {
     /// The result of this method lets you match over the mutability values to obtain a proof, which can be used to access features that are only available for one mutability.
     ///
     /// Most notably, the `GenRef::gen_{into,from}_{mut,shared}` methods require a proof of this form.
     fn mutability() -> MutabilityEnum<Self>;
+
+    /// The same proof that is returned by `mutability()`, available in `const` contexts.
+    const MUTABILITY: MutabilityEnum<Self>;
+
+    /// `true` if `Self` is `Mutable`, `false` if `Self` is `Shared`.
+    ///
+    /// This is the inverse of `BoolMutability`.
+    const IS_MUTABLE: bool;
+
+    /// `true` if `Self` is `Shared`, `false` if `Self` is `Mutable`.
+    const IS_SHARED: bool = !Self::IS_MUTABLE;
+
+    /// The weaker of `Self` and `N`: `Mutable` if both are `Mutable`, `Shared` otherwise.
+    ///
+    /// Usually accessed via the `Min<M1, M2>` type alias.
+    type Min<N: Mutability>: Mutability;
+
+    /// The stronger of `Self` and `N`: `Shared` if both are `Shared`, `Mutable` otherwise.
+    ///
+    /// Usually accessed via the `Max<M1, M2>` type alias.
+    type Max<N: Mutability>: Mutability;
+
+    /// The native reference type with this mutability: `&'a T` if `Self` is `Shared`, `&'a mut T` if `Self` is `Mutable`.
+    ///
+    /// A `GenRef<'a, M, T>` can be converted to and from `M::Ref<'a, T>` using `GenRef::into_native` and `GenRef::from_native`.
+    type Ref<'a, T: ?Sized + 'a>;
+
+    /// The native raw pointer type with this mutability: `*const T` if `Self` is `Shared`, `*mut T` if `Self` is `Mutable`.
+    type Ptr<T: ?Sized>;
+
+    /// Selects one of two types based on the mutability: `S` if `Self` is `Shared`, `Mu` if `Self` is `Mutable`.
+    ///
+    /// This allows naming types that differ between the two mutabilities, such as `slice::Iter` and `slice::IterMut`, in a generic context.
+    /// Values of this type can be created with `MutabilityEnum::select` or `Mutability::select_with`, and unwrapped with `IsShared::unselect` or `IsMutable::unselect`.
+    type Select<S, Mu>;
+
+    #[inline]
+    /// Creates a value of type `Self::Select<S, Mu>` by calling either `f_shared` or `f_mut`, depending on the mutability.
+    ///
+    /// Each closure receives a proof of the mutability of `Self`.
+    /// As both closures are constructed, they can not both capture the same value by move (such as a `GenRef`).
+    /// In that case, `match` on `Self::mutability()` and call `select` on the proof instead.
+    fn select_with<S, Mu>(
+        f_shared: impl FnOnce(IsShared<Self>) -> S,
+        f_mut: impl FnOnce(IsMutable<Self>) -> Mu,
+    ) -> Self::Select<S, Mu> {
+        match Self::mutability() {
+            MutabilityEnum::Shared(proof) => proof.select(f_shared(proof)),
+            MutabilityEnum::Mutable(proof) => proof.select(f_mut(proof)),
+        }
+    }
+}
+
+/// The weaker of two mutability parameters: `Mutable` if both `M1` and `M2` are `Mutable`, `Shared` otherwise.
+///
+/// This is the mutability of a reference derived from both a `GenRef<'_, M1, _>` and a `GenRef<'_, M2, _>`.
+/// A `GenRef<'_, M1, T>` can be converted into a `GenRef<'_, Min<M1, M2>, T>` using `GenRef::weaken`.
+pub type Min<M1, M2> = <M1 as Mutability>::Min<M2>;
+
+/// The stronger of two mutability parameters: `Shared` if both `M1` and `M2` are `Shared`, `Mutable` otherwise.
+pub type Max<M1, M2> = <M1 as Mutability>::Max<M2>;
+
+/// Type-level minimum of two mutability parameters, in trait form.
+///
+/// This trait is implemented for all pairs of mutability parameters, `<M1 as MutabilityMin<M2>>::Output` is the same type as `Min<M1, M2>`.
+pub trait MutabilityMin<N: Mutability>: Mutability {
+    /// The weaker of `Self` and `N`.
+    type Output: Mutability;
+}
+impl<M: Mutability, N: Mutability> MutabilityMin<N> for M {
+    type Output = Min<M, N>;
+}
+
+/// Type-level maximum of two mutability parameters, in trait form.
+///
+/// This trait is implemented for all pairs of mutability parameters, `<M1 as MutabilityMax<M2>>::Output` is the same type as `Max<M1, M2>`.
+pub trait MutabilityMax<N: Mutability>: Mutability {
+    /// The stronger of `Self` and `N`.
+    type Output: Mutability;
+}
+impl<M: Mutability, N: Mutability> MutabilityMax<N> for M {
+    type Output = Max<M, N>;
 }
 
 /// Represents the mutability of a shared reference, `&T`.
@@ -44,13 +138,23 @@ unsafe impl Mutability for Shared {
 
         MutabilityEnum::Shared(proof)
     }
+
+    const MUTABILITY: MutabilityEnum<Self> = MutabilityEnum::Shared(Shared::mutability());
+    const IS_MUTABLE: bool = false;
+
+    type Min<N: Mutability> = Shared;
+    type Max<N: Mutability> = N;
+
+    type Ref<'a, T: ?Sized + 'a> = &'a T;
+    type Ptr<T: ?Sized> = *const T;
+    type Select<S, Mu> = S;
 }
 impl Shared {
     #[inline]
     /// This method returns a proof for the shared-ness of `Shared`.
     /// Note: this method shadows `<Shared as Mutability>::mutability()`, which returns the same proof wrapped in `MutabilityEnum::Shared`.
     /// If you have access to this method (i.e. in non-generic contexts), you should not need `<Shared as Mutability>::mutability()`.
-    pub fn mutability() -> IsShared<Shared> {
+    pub const fn mutability() -> IsShared<Shared> {
         // SAFETY: `M` is `Shared`
         unsafe { IsShared::new() }
     }
@@ -73,13 +177,23 @@ unsafe impl Mutability for Mutable {
 
         MutabilityEnum::Mutable(proof)
     }
+
+    const MUTABILITY: MutabilityEnum<Self> = MutabilityEnum::Mutable(Mutable::mutability());
+    const IS_MUTABLE: bool = true;
+
+    type Min<N: Mutability> = N;
+    type Max<N: Mutability> = Mutable;
+
+    type Ref<'a, T: ?Sized + 'a> = &'a mut T;
+    type Ptr<T: ?Sized> = *mut T;
+    type Select<S, Mu> = Mu;
 }
 impl Mutable {
     #[inline]
     /// This method returns a proof for the mutable-ness of `Mutable`.
     /// Note: this method shadows `<Mutable as Mutability>::mutability()`, which returns the same proof wrapped in `MutabilityEnum::Mutable`.
     /// If you have access to this method (i.e. in non-generic contexts), you should not need `<Mutable as Mutability>::mutability()`.
-    pub fn mutability() -> IsMutable<Mutable> {
+    pub const fn mutability() -> IsMutable<Mutable> {
         // SAFETY: `M` is `Mutable`
         unsafe { IsMutable::new() }
     }
@@ -96,9 +210,61 @@ pub struct IsMutable<M: Mutability>(PhantomData<M>);
 impl<M: Mutability> IsMutable<M> {
     #[inline]
     // SAFETY: `M` must be `Mutable`
-    pub(crate) unsafe fn new() -> Self {
+    pub(crate) const unsafe fn new() -> Self {
         IsMutable(PhantomData)
     }
+
+    #[inline]
+    /// If the weaker of `M` and `N` is `Mutable`, both of them are `Mutable`.
+    ///
+    /// This splits a proof about `Min<M, N>` into proofs about `M` and `N`.
+    pub const fn split_min<N: Mutability>(
+        _proof: IsMutable<Min<M, N>>,
+    ) -> (IsMutable<M>, IsMutable<N>) {
+        // SAFETY: `Min<M, N>` is only `Mutable` if both `M` and `N` are `Mutable`.
+        let proof_m = unsafe { IsMutable::new() };
+        // SAFETY: see above.
+        let proof_n = unsafe { IsMutable::new() };
+        (proof_m, proof_n)
+    }
+
+    #[inline]
+    /// If `M` is `Mutable`, the stronger of `M` and any `N` is `Mutable` as well.
+    pub const fn into_max<N: Mutability>(self) -> IsMutable<Max<M, N>> {
+        // SAFETY: `M` is `Mutable`, so `Max<M, N>` is `Mutable`.
+        unsafe { IsMutable::new() }
+    }
+
+    #[inline]
+    /// Wraps a value of type `Mu` into `M::Select<S, Mu>`, which is the same type as `Mu` when `M` is `Mutable`.
+    pub fn select<S, Mu>(self, value: Mu) -> M::Select<S, Mu> {
+        // SAFETY: `M` is `Mutable`, so `M::Select<S, Mu>` is `Mu`.
+        unsafe { transmute_unchecked::<Mu, M::Select<S, Mu>>(value) }
+    }
+
+    #[inline]
+    /// Unwraps a value of type `M::Select<S, Mu>` into `Mu`, which is the same type when `M` is `Mutable`.
+    pub fn unselect<S, Mu>(self, value: M::Select<S, Mu>) -> Mu {
+        // SAFETY: `M` is `Mutable`, so `M::Select<S, Mu>` is `Mu`.
+        unsafe { transmute_unchecked::<M::Select<S, Mu>, Mu>(value) }
+    }
+
+    #[inline]
+    /// Converts a value of type `F::Apply<M>` into `F::Apply<Mutable>`.
+    ///
+    /// The proof acts as a type-equality witness: as `M` is `Mutable`, the two types are the same, so this conversion is a no-op.
+    /// See `MutabilityFamily` for how to describe the type to be converted.
+    pub fn cast<F: MutabilityFamily>(self, value: F::Apply<M>) -> F::Apply<Mutable> {
+        // SAFETY: `M` is `Mutable`, so `F::Apply<M>` is `F::Apply<Mutable>`.
+        unsafe { transmute_unchecked::<F::Apply<M>, F::Apply<Mutable>>(value) }
+    }
+
+    #[inline]
+    /// Converts a value of type `F::Apply<Mutable>` into `F::Apply<M>`. This is the inverse of `cast`.
+    pub fn uncast<F: MutabilityFamily>(self, value: F::Apply<Mutable>) -> F::Apply<M> {
+        // SAFETY: `M` is `Mutable`, so `F::Apply<M>` is `F::Apply<Mutable>`.
+        unsafe { transmute_unchecked::<F::Apply<Mutable>, F::Apply<M>>(value) }
+    }
 }
 
 /// The existence of a value of this type guarantees that a specific mutability parameter `M` is `Shared`.
@@ -112,9 +278,61 @@ pub struct IsShared<M: Mutability>(PhantomData<M>);
 impl<M: Mutability> IsShared<M> {
     #[inline]
     // SAFETY: `M` must be `Shared`
-    pub(crate) unsafe fn new() -> Self {
+    pub(crate) const unsafe fn new() -> Self {
         IsShared(PhantomData)
     }
+
+    #[inline]
+    /// If the stronger of `M` and `N` is `Shared`, both of them are `Shared`.
+    ///
+    /// This splits a proof about `Max<M, N>` into proofs about `M` and `N`.
+    pub const fn split_max<N: Mutability>(
+        _proof: IsShared<Max<M, N>>,
+    ) -> (IsShared<M>, IsShared<N>) {
+        // SAFETY: `Max<M, N>` is only `Shared` if both `M` and `N` are `Shared`.
+        let proof_m = unsafe { IsShared::new() };
+        // SAFETY: see above.
+        let proof_n = unsafe { IsShared::new() };
+        (proof_m, proof_n)
+    }
+
+    #[inline]
+    /// If `M` is `Shared`, the weaker of `M` and any `N` is `Shared` as well.
+    pub const fn into_min<N: Mutability>(self) -> IsShared<Min<M, N>> {
+        // SAFETY: `M` is `Shared`, so `Min<M, N>` is `Shared`.
+        unsafe { IsShared::new() }
+    }
+
+    #[inline]
+    /// Wraps a value of type `S` into `M::Select<S, Mu>`, which is the same type as `S` when `M` is `Shared`.
+    pub fn select<S, Mu>(self, value: S) -> M::Select<S, Mu> {
+        // SAFETY: `M` is `Shared`, so `M::Select<S, Mu>` is `S`.
+        unsafe { transmute_unchecked::<S, M::Select<S, Mu>>(value) }
+    }
+
+    #[inline]
+    /// Unwraps a value of type `M::Select<S, Mu>` into `S`, which is the same type when `M` is `Shared`.
+    pub fn unselect<S, Mu>(self, value: M::Select<S, Mu>) -> S {
+        // SAFETY: `M` is `Shared`, so `M::Select<S, Mu>` is `S`.
+        unsafe { transmute_unchecked::<M::Select<S, Mu>, S>(value) }
+    }
+
+    #[inline]
+    /// Converts a value of type `F::Apply<M>` into `F::Apply<Shared>`.
+    ///
+    /// The proof acts as a type-equality witness: as `M` is `Shared`, the two types are the same, so this conversion is a no-op.
+    /// See `MutabilityFamily` for how to describe the type to be converted.
+    pub fn cast<F: MutabilityFamily>(self, value: F::Apply<M>) -> F::Apply<Shared> {
+        // SAFETY: `M` is `Shared`, so `F::Apply<M>` is `F::Apply<Shared>`.
+        unsafe { transmute_unchecked::<F::Apply<M>, F::Apply<Shared>>(value) }
+    }
+
+    #[inline]
+    /// Converts a value of type `F::Apply<Shared>` into `F::Apply<M>`. This is the inverse of `cast`.
+    pub fn uncast<F: MutabilityFamily>(self, value: F::Apply<Shared>) -> F::Apply<M> {
+        // SAFETY: `M` is `Shared`, so `F::Apply<M>` is `F::Apply<Shared>`.
+        unsafe { transmute_unchecked::<F::Apply<Shared>, F::Apply<M>>(value) }
+    }
 }
 
 /// This enum makes it possible to `match` over a mutability parameter.
@@ -132,3 +350,125 @@ pub enum MutabilityEnum<M: Mutability> {
     /// Contains a proof that `M` is `Shared`. `MutabilityEnum::Shared` (this enum variant) is not to be confused with `Shared` (type implementing `Mutability`).
     Shared(IsShared<M>),
 }
+
+impl<M: Mutability> MutabilityEnum<M> {
+    #[inline]
+    /// Selects `shared_val` if `M` is `Shared` and `mut_val` if `M` is `Mutable`, returning it as `M::Select<S, Mu>`.
+    ///
+    /// Both values are evaluated eagerly, the one that is not selected is dropped.
+    /// To only construct the selected value, use `Mutability::select_with`.
+    pub fn select<S, Mu>(self, shared_val: S, mut_val: Mu) -> M::Select<S, Mu> {
+        match self {
+            MutabilityEnum::Shared(proof) => proof.select(shared_val),
+            MutabilityEnum::Mutable(proof) => proof.select(mut_val),
+        }
+    }
+}
+
+/// Bridges `const MUT: bool` generic parameters to mutability parameters.
+///
+/// `BoolMutability<false>` maps to `Shared` and `BoolMutability<true>` maps to `Mutable` through the `BoolToMutability` trait.
+/// The `MutabilityOf<MUT>` type alias is a shorthand for this mapping.
+/// The opposite direction is available as `Mutability::IS_MUTABLE`.
+///
+/// In a context generic over `const MUT: bool`, the `BoolMutability<MUT>: BoolToMutability` bound is required to use the mapping.
+///
+/// ```
+/// # use generic_mutability::{BoolMutability, BoolToMutability, GenRef, MutabilityOf};
+/// struct Buffer<'a, const MUT: bool>
+/// where
+///     BoolMutability<MUT>: BoolToMutability,
+/// {
+///     data: GenRef<'a, MutabilityOf<MUT>, [u8]>,
+/// }
+/// ```
+pub enum BoolMutability<const MUT: bool> {}
+
+/// Maps `BoolMutability<MUT>` to the corresponding mutability parameter. See `BoolMutability`.
+///
+/// This trait is implemented for `BoolMutability<false>` and `BoolMutability<true>`.
+pub trait BoolToMutability {
+    /// `Shared` for `BoolMutability<false>`, `Mutable` for `BoolMutability<true>`.
+    type Mutability: Mutability;
+}
+impl BoolToMutability for BoolMutability<false> {
+    type Mutability = Shared;
+}
+impl BoolToMutability for BoolMutability<true> {
+    type Mutability = Mutable;
+}
+
+/// The mutability parameter corresponding to `MUT`: `Shared` if it is `false`, `Mutable` if it is `true`. See `BoolMutability`.
+pub type MutabilityOf<const MUT: bool> = <BoolMutability<MUT> as BoolToMutability>::Mutability;
+
+/// A type constructor that takes a mutability parameter, such as `GenRef<'a, _, T>` or a user-defined `Foo<_>`.
+///
+/// This is used to convert values whose type depends on a mutability parameter `M` after proving the value of `M`, using `IsMutable::cast` and `IsShared::cast`.
+///
+/// Implementations are provided for common shapes: `GenRefFamily` and `RefFamily` for references, and `OptionFamily` (as well as `VecFamily` with the `std` feature) to wrap other families.
+/// For your own types, implement this trait on a marker type:
+///
+/// ```
+/// # use core::marker::PhantomData;
+/// # use generic_mutability::{GenRef, IsMutable, Mutability, MutabilityFamily, Mutable};
+/// struct Foo<'a, M: Mutability> {
+///     value: GenRef<'a, M, i32>,
+/// }
+///
+/// struct FooFamily<'a>(PhantomData<&'a ()>);
+/// impl<'a> MutabilityFamily for FooFamily<'a> {
+///     type Apply<M: Mutability> = Foo<'a, M>;
+/// }
+///
+/// fn increment<M: Mutability>(foo: Foo<'_, M>, proof: IsMutable<M>) {
+///     let foo: Foo<'_, Mutable> = proof.cast::<FooFamily>(foo);
+///     *GenRef::into_mut(foo.value) += 1;
+/// }
+/// ```
+pub trait MutabilityFamily {
+    /// The type obtained by applying this type constructor to the mutability parameter `M`.
+    type Apply<M: Mutability>;
+}
+
+/// The family of `GenRef<'a, M, T>` types. See `MutabilityFamily`.
+pub struct GenRefFamily<'a, T: ?Sized>(PhantomData<&'a T>);
+impl<'a, T: ?Sized> MutabilityFamily for GenRefFamily<'a, T> {
+    type Apply<M: Mutability> = GenRef<'a, M, T>;
+}
+
+/// The family of native `M::Ref<'a, T>` types (`&'a T` and `&'a mut T`). See `MutabilityFamily`.
+pub struct RefFamily<'a, T: ?Sized>(PhantomData<&'a T>);
+impl<'a, T: ?Sized> MutabilityFamily for RefFamily<'a, T> {
+    type Apply<M: Mutability> = M::Ref<'a, T>;
+}
+
+/// The family of `Option<F::Apply<M>>` types, for example `Option<GenRef<'a, M, T>>`. See `MutabilityFamily`.
+pub struct OptionFamily<F: MutabilityFamily>(PhantomData<F>);
+impl<F: MutabilityFamily> MutabilityFamily for OptionFamily<F> {
+    type Apply<M: Mutability> = Option<F::Apply<M>>;
+}
+
+#[cfg(any(feature = "std", doc))]
+/// The family of `Vec<F::Apply<M>>` types, for example `Vec<GenRef<'a, M, T>>`. See `MutabilityFamily`.
+///
+/// This is only available with the feature flag `std`.
+pub struct VecFamily<F: MutabilityFamily>(PhantomData<F>);
+#[cfg(any(feature = "std", doc))]
+impl<F: MutabilityFamily> MutabilityFamily for VecFamily<F> {
+    type Apply<M: Mutability> = std::vec::Vec<F::Apply<M>>;
+}
+
+#[inline]
+/// Reinterprets a value of type `A` as a value of type `B`.
+///
+/// This is used to convert between types that are only known to be equal by a proof, such as `M::Ref<'a, T>` and `&'a mut T` given an `IsMutable<M>`.
+///
+/// # Safety
+///
+/// `A` and `B` must be the same type.
+pub(crate) unsafe fn transmute_unchecked<A, B>(value: A) -> B {
+    let value = ManuallyDrop::new(value);
+
+    // SAFETY: the caller guarantees that `A` and `B` are the same type, and `value` is never dropped, so it is effectively moved.
+    unsafe { mem::transmute_copy::<ManuallyDrop<A>, B>(&value) }
+}
@@ -0,0 +1,80 @@
+use crate::{GenRef, Mutability};
+
+/// Types holding exclusive or generic references that can be reborrowed.
+///
+/// Reborrowing creates a shorter-lived copy of the value from a mutable reference to it, which can be passed to a function without consuming the original.
+/// This generalizes `GenRef::reborrow` (and the implicit reborrowing of `&mut T`) to other types, like structs holding multiple `GenRef`s.
+///
+/// For `GenRef`, this trait provides the method variant of `GenRef::reborrow`.
+///
+/// The implementation for a struct can be derived with `#[derive(Reborrow)]` (requires the feature flag `macros`).
+///
+/// ```rust
+/// # use generic_mutability::{GenRef, Mutability, Reborrow};
+/// fn gen_increment<M: Mutability>(counters: Option<GenRef<'_, M, [u32]>>) {
+///     # let _ = counters;
+///     // ...
+/// }
+///
+/// fn gen_increment_twice<M: Mutability>(mut counters: Option<GenRef<'_, M, [u32]>>) {
+///     gen_increment(counters.reborrow());
+///     gen_increment(counters);
+/// }
+/// ```
+pub trait Reborrow {
+    /// The type of the reborrowed value, with its lifetime replaced by `'r`.
+    type Reborrowed<'r>
+    where
+        Self: 'r;
+
+    /// Creates a shorter-lived copy of the value, which borrows from `self`.
+    fn reborrow(&mut self) -> Self::Reborrowed<'_>;
+}
+
+impl<'s, M: Mutability, T: ?Sized> Reborrow for GenRef<'s, M, T> {
+    type Reborrowed<'r>
+        = GenRef<'r, M, T>
+    where
+        Self: 'r;
+
+    #[inline]
+    fn reborrow(&mut self) -> Self::Reborrowed<'_> {
+        GenRef::reborrow(self)
+    }
+}
+
+impl<T: ?Sized> Reborrow for &mut T {
+    type Reborrowed<'r>
+        = &'r mut T
+    where
+        Self: 'r;
+
+    #[inline]
+    fn reborrow(&mut self) -> Self::Reborrowed<'_> {
+        self
+    }
+}
+
+impl<T: ?Sized> Reborrow for &T {
+    type Reborrowed<'r>
+        = &'r T
+    where
+        Self: 'r;
+
+    #[inline]
+    fn reborrow(&mut self) -> Self::Reborrowed<'_> {
+        self
+    }
+}
+
+impl<R: Reborrow> Reborrow for Option<R> {
+    type Reborrowed<'r>
+        = Option<R::Reborrowed<'r>>
+    where
+        Self: 'r;
+
+    #[inline]
+    fn reborrow(&mut self) -> Self::Reborrowed<'_> {
+        self.as_mut().map(|value| value.reborrow())
+    }
+}
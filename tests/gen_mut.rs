@@ -0,0 +1,236 @@
+#![cfg(test)]
+
+//! Exercises every sub-macro form of `gen_mut!`, both with a single and with multiple mutability parameters.
+//! Each function is instantiated in both the shared and the mutable mode.
+
+use generic_mutability::*;
+
+struct Pair {
+    first: i32,
+    second: i32,
+}
+
+fn into_gen_expr<M: Mutability>(genref: GenRef<'_, M, Pair>) -> GenRef<'_, M, i32> {
+    gen_mut! {M => {
+        let pair = from_gen!(genref);
+        into_gen!(switch_shared_mut![&pair.first, &mut pair.first])
+    }}
+}
+
+fn into_gen_place<M: Mutability>(genref: GenRef<'_, M, Pair>) -> GenRef<'_, M, i32> {
+    gen_mut! {M => {
+        let pair = from_gen!(genref);
+        into_gen!(&gen pair.second)
+    }}
+}
+
+fn into_gen_closure<M: Mutability>(genref: GenRef<'_, M, [i32]>) -> Option<GenRef<'_, M, i32>> {
+    gen_mut! {M => {
+        switch_shared_mut![<[_]>::first, <[_]>::first_mut](from_gen!(genref)).map(into_gen!())
+    }}
+}
+
+fn from_gen_closure<M: Mutability>(
+    genref: Option<GenRef<'_, M, i32>>,
+) -> Option<GenRef<'_, M, i32>> {
+    gen_mut! {M => {
+        genref.map(from_gen!()).map(into_gen!())
+    }}
+}
+
+fn switch_expr<M: Mutability>() -> &'static str {
+    gen_mut! {M => {
+        switch_shared_mut!("shared", "mutable")
+    }}
+}
+
+fn switch_tt_expr<M: Mutability>() -> &'static str {
+    gen_mut! {M => {
+        switch_shared_mut!({ "shared" } { "mutable" })
+    }}
+}
+
+fn switch_tt_statements<M: Mutability>(genref: GenRef<'_, M, i32>) -> i32 {
+    gen_mut! {M => {
+        let value = from_gen!(genref);
+        switch_shared_mut! {
+            { let result = *value; }
+            { *value += 1; let result = *value; }
+        }
+        result
+    }}
+}
+
+fn switch_tt_items<M: Mutability>(genref: GenRef<'_, M, [i32]>) -> usize {
+    gen_mut! {M => {
+        switch_shared_mut! {
+            { use core::slice::Iter as Iter; }
+            { use core::slice::IterMut as Iter; }
+        }
+        let iter: Iter<'_, i32> = switch_shared_mut![<[_]>::iter, <[_]>::iter_mut](from_gen!(genref));
+        iter.count()
+    }}
+}
+
+fn switch_tt_impl<M: Mutability>() -> &'static str {
+    gen_mut! {M => {
+        struct Mode;
+        switch_shared_mut! {
+            { impl Mode { fn name(&self) -> &'static str { "shared" } } }
+            { impl Mode { fn name(&self) -> &'static str { "mutable" } } }
+        }
+        Mode.name()
+    }}
+}
+
+fn either_roundtrip<M: Mutability>(genref: GenRef<'_, M, [i32]>) -> Option<GenRef<'_, M, i32>> {
+    let either = gen_mut! {M => {
+        gen_either!(switch_shared_mut![<[_]>::iter, <[_]>::iter_mut](from_gen!(genref)))
+    }};
+    gen_mut! {M => {
+        from_either!(either).next_back().map(into_gen!())
+    }}
+}
+
+#[test]
+fn single_parameter_shared() {
+    let pair = Pair {
+        first: 1,
+        second: 2,
+    };
+    let array = [1, 2, 3];
+
+    assert_eq!(*into_gen_expr(GenRef::from(&pair)), 1);
+    assert_eq!(*into_gen_place(GenRef::from(&pair)), 2);
+    assert_eq!(*into_gen_closure(GenRef::from(&array[..])).unwrap(), 1);
+    assert_eq!(*from_gen_closure(Some(GenRef::from(&array[1]))).unwrap(), 2);
+    assert_eq!(switch_expr::<Shared>(), "shared");
+    assert_eq!(switch_tt_expr::<Shared>(), "shared");
+    assert_eq!(switch_tt_statements(GenRef::from(&array[0])), 1);
+    assert_eq!(switch_tt_items(GenRef::from(&array[..])), 3);
+    assert_eq!(switch_tt_impl::<Shared>(), "shared");
+    assert_eq!(*either_roundtrip(GenRef::from(&array[..])).unwrap(), 3);
+}
+
+#[test]
+fn single_parameter_mutable() {
+    let mut pair = Pair {
+        first: 1,
+        second: 2,
+    };
+    let mut array = [1, 2, 3];
+
+    *into_gen_expr(GenRef::from(&mut pair)) = 10;
+    *into_gen_place(GenRef::from(&mut pair)) = 20;
+    assert_eq!((pair.first, pair.second), (10, 20));
+
+    *into_gen_closure(GenRef::from(&mut array[..])).unwrap() = 10;
+    *from_gen_closure(Some(GenRef::from(&mut array[1]))).unwrap() = 20;
+    assert_eq!(switch_expr::<Mutable>(), "mutable");
+    assert_eq!(switch_tt_expr::<Mutable>(), "mutable");
+    assert_eq!(switch_tt_statements(GenRef::from(&mut array[2])), 4);
+    assert_eq!(switch_tt_items(GenRef::from(&mut array[..])), 3);
+    assert_eq!(switch_tt_impl::<Mutable>(), "mutable");
+    *either_roundtrip(GenRef::from(&mut array[..])).unwrap() = 30;
+    assert_eq!(array, [10, 20, 30]);
+}
+
+type OptionPair<'a, M1, M2> = (Option<GenRef<'a, M1, i32>>, Option<GenRef<'a, M2, i32>>);
+
+#[allow(clippy::type_complexity)]
+fn multi_into_gen<'a, M1: Mutability, M2: Mutability>(
+    first: GenRef<'a, M1, Pair>,
+    second: GenRef<'a, M2, [i32]>,
+) -> (
+    GenRef<'a, M1, i32>,
+    GenRef<'a, M2, i32>,
+    Option<GenRef<'a, M2, i32>>,
+) {
+    gen_mut! {M1, M2 => {
+        let pair = from_gen!(M1: first);
+        let slice = from_gen!(M2: second);
+        let (head, tail) = switch_shared_mut![M2: <[_]>::split_first, <[_]>::split_first_mut](slice).unwrap();
+        (
+            into_gen!(M1: &gen pair.first),
+            into_gen!(M2: head),
+            switch_shared_mut![M2: <[_]>::first, <[_]>::first_mut](tail).map(into_gen!(M2:)),
+        )
+    }}
+}
+
+fn multi_from_gen_closure<'a, M1: Mutability, M2: Mutability>(
+    first: Option<GenRef<'a, M1, i32>>,
+    second: Option<GenRef<'a, M2, i32>>,
+) -> OptionPair<'a, M1, M2> {
+    gen_mut! {M1, M2 => {
+        (
+            first.map(from_gen!(M1:)).map(into_gen!(M1:)),
+            second.map(from_gen!(M2:)).map(into_gen!(M2:)),
+        )
+    }}
+}
+
+fn multi_switch<M1: Mutability, M2: Mutability>() -> (&'static str, &'static str) {
+    gen_mut! {M1, M2 => {
+        struct Mode;
+        switch_shared_mut! {M1:
+            { impl Mode { fn name(&self) -> &'static str { "shared" } } }
+            { impl Mode { fn name(&self) -> &'static str { "mutable" } } }
+        }
+        assert_eq!(Mode.name(), switch_shared_mut!(M1: "shared", "mutable"));
+        (
+            Mode.name(),
+            switch_shared_mut!(M2: { "shared" } { "mutable" }),
+        )
+    }}
+}
+
+fn multi_either<'a, M1: Mutability, M2: Mutability>(
+    first: GenRef<'a, M1, [i32]>,
+    second: GenRef<'a, M2, [i32]>,
+) -> OptionPair<'a, M1, M2> {
+    let (first, second) = gen_mut! {M1, M2 => {
+        (
+            gen_either!(M1: switch_shared_mut![M1: <[_]>::iter, <[_]>::iter_mut](from_gen!(M1: first))),
+            gen_either!(M2: switch_shared_mut![M2: <[_]>::iter, <[_]>::iter_mut](from_gen!(M2: second))),
+        )
+    }};
+    gen_mut! {M1, M2 => {
+        (
+            from_either!(M1: first).last().map(into_gen!(M1:)),
+            from_either!(M2: second).last().map(into_gen!(M2:)),
+        )
+    }}
+}
+
+#[test]
+fn multiple_parameters_mixed() {
+    let pair = Pair {
+        first: 1,
+        second: 2,
+    };
+    let mut array = [3, 4];
+
+    let (first, mut head, tail) = multi_into_gen(GenRef::from(&pair), GenRef::from(&mut array[..]));
+    assert_eq!(*first, 1);
+    *head = 30;
+    assert_eq!(*tail.unwrap(), 4);
+    assert_eq!(array, [30, 4]);
+
+    let (first, second) = multi_from_gen_closure(
+        Some(GenRef::from(&pair.second)),
+        Some(GenRef::from(&mut array[1])),
+    );
+    *second.unwrap() = 40;
+    assert_eq!(*first.unwrap(), 2);
+    assert_eq!(array, [30, 40]);
+
+    assert_eq!(multi_switch::<Shared, Mutable>(), ("shared", "mutable"));
+    assert_eq!(multi_switch::<Mutable, Shared>(), ("mutable", "shared"));
+
+    let other = [5, 6];
+    let (first, second) = multi_either(GenRef::from(&mut array[..]), GenRef::from(&other[..]));
+    *first.unwrap() = 0;
+    assert_eq!(*second.unwrap(), 6);
+    assert_eq!(array, [30, 0]);
+}
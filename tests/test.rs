@@ -1,6 +1,7 @@
 #![cfg(test)]
 #![allow(clippy::disallowed_names)]
 
+use core::future::Future;
 use generic_mutability::*;
 
 fn gen_index<M: Mutability>(gen_vec: GenRef<'_, M, Vec<i32>>, idx: usize) -> GenRef<'_, M, i32> {
@@ -53,3 +54,565 @@ fn field_access_macro() {
 
     assert_eq!(b, &1)
 }
+
+fn gen_first<'a, M1: Mutability, M2: Mutability>(
+    first: GenRef<'a, M1, i32>,
+    _second: GenRef<'a, M2, i32>,
+) -> GenRef<'a, Min<M1, M2>, i32> {
+    GenRef::weaken(first)
+}
+
+#[test]
+fn weaken_to_min() {
+    let mut a = 1;
+    let mut b = 2;
+
+    let shared: GenRef<'_, Shared, i32> = gen_first(GenRef::from(&mut a), GenRef::from(&b));
+    assert_eq!(shared, &1);
+
+    let mut mutable: GenRef<'_, Mutable, i32> =
+        gen_first(GenRef::from(&mut a), GenRef::from(&mut b));
+    *mutable = 3;
+    assert_eq!(a, 3);
+}
+
+struct GenSlot<'a, M: Mutability> {
+    value: M::Ref<'a, i32>,
+}
+
+fn gen_slot<M: Mutability>(gen_value: GenRef<'_, M, i32>) -> GenSlot<'_, M> {
+    GenSlot {
+        value: GenRef::into_native(gen_value),
+    }
+}
+
+#[test]
+fn native_reference_roundtrip() {
+    let mut value = 1;
+
+    let slot = gen_slot(GenRef::from(&mut value));
+    *slot.value += 1;
+    let gen_value: GenRef<'_, Mutable, i32> = GenRef::from_native(slot.value);
+    assert_eq!(gen_value, &2);
+
+    let slot = gen_slot(GenRef::from(&value));
+    let shared: &i32 = slot.value;
+    assert_eq!(shared, &2);
+}
+
+fn gen_iter<M: Mutability>(
+    gen_slice: GenRef<'_, M, [i32]>,
+) -> M::Select<core::slice::Iter<'_, i32>, core::slice::IterMut<'_, i32>> {
+    match M::mutability() {
+        MutabilityEnum::Shared(proof) => {
+            proof.select(GenRef::gen_into_shared(gen_slice, proof).iter())
+        }
+        MutabilityEnum::Mutable(proof) => {
+            proof.select(GenRef::gen_into_mut(gen_slice, proof).iter_mut())
+        }
+    }
+}
+
+#[test]
+fn select_iterator_type() {
+    let mut array = [1, 2, 3];
+
+    for elem in gen_iter(GenRef::from(&mut array[..])) {
+        *elem *= 2;
+    }
+    let sum: i32 = gen_iter(GenRef::from(&array[..])).sum();
+    assert_eq!(sum, 12);
+
+    let selected: <Shared as Mutability>::Select<&str, i32> =
+        <Shared as Mutability>::mutability().select("shared", 1);
+    assert_eq!(selected, "shared");
+
+    let selected: <Mutable as Mutability>::Select<&str, i32> =
+        Mutable::select_with(|_| "shared", |_| 1);
+    assert_eq!(selected, 1);
+}
+
+fn gen_reset_first<M: Mutability>(gen_elems: Option<GenRef<'_, M, i32>>) {
+    if let MutabilityEnum::Mutable(proof) = M::mutability() {
+        let elems: Option<GenRef<'_, Mutable, i32>> =
+            proof.cast::<OptionFamily<GenRefFamily<i32>>>(gen_elems);
+        if let Some(mut elem) = elems {
+            *elem = 0;
+        }
+    }
+}
+
+#[test]
+fn cast_with_proof() {
+    let mut value = 1;
+    gen_reset_first(Some(GenRef::from(&value)));
+    assert_eq!(value, 1);
+    gen_reset_first(Some(GenRef::from(&mut value)));
+    assert_eq!(value, 0);
+}
+
+#[cfg(feature = "std")]
+fn gen_increment_all<'a, M: Mutability>(
+    gen_elems: Vec<GenRef<'a, M, i32>>,
+) -> Vec<GenRef<'a, M, i32>> {
+    match M::mutability() {
+        MutabilityEnum::Mutable(proof) => {
+            let mut elems: Vec<GenRef<'a, Mutable, i32>> =
+                proof.cast::<VecFamily<GenRefFamily<i32>>>(gen_elems);
+            for elem in &mut elems {
+                **elem += 1;
+            }
+            proof.uncast::<VecFamily<GenRefFamily<i32>>>(elems)
+        }
+        MutabilityEnum::Shared(proof) => {
+            let elems: Vec<GenRef<'a, Shared, i32>> =
+                proof.cast::<VecFamily<GenRefFamily<i32>>>(gen_elems);
+            proof.uncast::<VecFamily<GenRefFamily<i32>>>(elems)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn cast_vec_with_proof() {
+    let mut values = [1, 2];
+    let elems: Vec<GenRef<'_, Mutable, i32>> = values.iter_mut().map(GenRef::from).collect();
+    let elems = gen_increment_all(elems);
+    assert_eq!(elems.len(), 2);
+    drop(elems);
+    assert_eq!(values, [2, 3]);
+
+    let elems: Vec<GenRef<'_, Shared, i32>> = values.iter().map(GenRef::from).collect();
+    let elems = gen_increment_all(elems);
+    let elems: Vec<&i32> = elems.into_iter().map(GenRef::into_shared).collect();
+    assert_eq!(elems, [&2, &3]);
+}
+
+const FIVE: GenRef<'static, Shared, i32> = GenRef::from_shared(&5);
+
+struct Buffer<'a, const MUT: bool>
+where
+    BoolMutability<MUT>: BoolToMutability,
+{
+    data: GenRef<'a, MutabilityOf<MUT>, [u8]>,
+}
+
+const fn is_mutable<M: Mutability>(_: &GenRef<'_, M, [u8]>) -> bool {
+    M::IS_MUTABLE
+}
+
+#[test]
+fn const_mutability() {
+    const { assert!(<Mutable as Mutability>::IS_MUTABLE) };
+    const { assert!(<Shared as Mutability>::IS_SHARED) };
+    assert_eq!(GenRef::into_shared(FIVE), &5);
+
+    let mut data = [1, 2, 3];
+    let buffer: Buffer<'_, true> = Buffer {
+        data: GenRef::from(&mut data[..]),
+    };
+    assert!(is_mutable(&buffer.data));
+    let buffer: Buffer<'_, false> = Buffer {
+        data: GenRef::from(&data[..]),
+    };
+    assert!(!is_mutable(&buffer.data));
+}
+
+#[cfg(feature = "std")]
+fn gen_write_hello<'s, M: WriteDispatch<'s, W>, W: ?Sized>(mut writer: GenRef<'s, M, W>) {
+    use std::io::Write;
+    writer.write_all(b"hello").unwrap();
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn write_dispatch() {
+    let mut buf = Vec::new();
+    gen_write_hello(GenRef::from(&mut buf));
+    assert_eq!(buf, b"hello");
+
+    gen_write_hello(GenRef::from(&std::io::sink()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn read_dispatch_shared_slice() {
+    use std::io::Read;
+    let data = [1, 2, 3];
+    let mut reader = GenRef::from(&data[..]);
+    let mut buf = [0; 2];
+    assert_eq!(reader.read(&mut buf).unwrap(), 2);
+    assert_eq!(buf, [1, 2]);
+    assert_eq!(reader.read(&mut buf).unwrap(), 1);
+    assert_eq!(buf[0], 3);
+
+    let mut rest = Vec::new();
+    GenRef::from(&data[1..]).read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, [2, 3]);
+}
+
+struct Countdown(core::cell::Cell<u32>);
+
+impl Iterator for &Countdown {
+    type Item = u32;
+    fn next(&mut self) -> Option<u32> {
+        let remaining = self.0.get().checked_sub(1)?;
+        self.0.set(remaining);
+        Some(remaining)
+    }
+}
+
+#[test]
+fn forwarded_traits() {
+    let countdown = Countdown(core::cell::Cell::new(3));
+    let collected: Vec<u32> = GenRef::from(&countdown).collect();
+    assert_eq!(collected, [2, 1, 0]);
+
+    let mut vec = vec![1, 2];
+    let mut gen_vec = GenRef::from(&mut vec);
+    gen_vec.extend([3]);
+    let slice: &[i32] = gen_vec.as_ref();
+    assert_eq!(slice, [1, 2, 3]);
+
+    let mut future = core::future::ready(5);
+    let mut gen_future = GenRef::from(&mut future);
+    let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    assert_eq!(
+        core::pin::Pin::new(&mut gen_future).poll(&mut cx),
+        core::task::Poll::Ready(5)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn partial_eq_owned() {
+    let vec = vec![1, 2, 3];
+    assert_eq!(GenRef::from(&vec[..]), vec);
+    assert_eq!(GenRef::from("text"), String::from("text"));
+    assert_eq!(GenRef::from(&1), Box::new(1));
+}
+
+fn gen_copy_first<'a, M1: Mutability, M2: Mutability>(
+    source: GenRef<'a, M1, Vec<i32>>,
+    target: GenRef<'a, M2, Vec<i32>>,
+) -> (GenRef<'a, M1, i32>, GenRef<'a, M2, i32>) {
+    gen_mut! { M1, M2 => {
+        let source = from_gen!(M1: source);
+        let target = from_gen!(M2: target);
+        switch_shared_mut!(M2: {} { target[0] = source[0]; });
+        let first = switch_shared_mut![M1: <[_]>::first, <[_]>::first_mut](source);
+        let first = first.map(into_gen!(M1:)).unwrap();
+        (first, into_gen!(M2: &gen target[0]))
+    }}
+}
+
+#[test]
+fn gen_mut_multiple_parameters() {
+    let mut source = vec![1];
+    let mut target = vec![2];
+
+    let (first, copied) = gen_copy_first(GenRef::from(&source), GenRef::from(&target));
+    assert_eq!(first, &1);
+    assert_eq!(copied, &2);
+
+    let (first, copied) = gen_copy_first(GenRef::from(&mut source), GenRef::from(&mut target));
+    assert_eq!(first, &1);
+    assert_eq!(copied, &1);
+}
+
+fn gen_iter_elems<'a, M: Mutability>(
+    gen_slice: GenRef<'a, M, [i32]>,
+) -> impl Iterator<Item = GenRef<'a, M, i32>> {
+    gen_mut! {M => {
+        let iter = switch_shared_mut![<[_]>::iter, <[_]>::iter_mut](from_gen!(gen_slice));
+        gen_either!(iter.map(into_gen!()))
+    }}
+}
+
+fn gen_slice_iter<'a, M: Mutability>(
+    gen_slice: GenRef<'a, M, [i32]>,
+) -> GenEither<M, core::slice::Iter<'a, i32>, core::slice::IterMut<'a, i32>> {
+    gen_mut! {M => {
+        gen_either!(switch_shared_mut![<[_]>::iter, <[_]>::iter_mut](from_gen!(gen_slice)))
+    }}
+}
+
+fn gen_last<'a, M: Mutability>(gen_slice: GenRef<'a, M, [i32]>) -> Option<GenRef<'a, M, i32>> {
+    let iter = gen_slice_iter(gen_slice);
+    gen_mut! {M => {
+        from_either!(iter).last().map(into_gen!())
+    }}
+}
+
+#[test]
+fn gen_either_branches() {
+    let mut array = [1, 2, 3];
+
+    for mut elem in gen_iter_elems(GenRef::from(&mut array[..])) {
+        *elem += 1;
+    }
+    assert_eq!(gen_last(GenRef::from(&array[..])).unwrap(), &4);
+    assert_eq!(gen_iter_elems(GenRef::from(&array[..])).count(), 3);
+
+    *gen_last(GenRef::from(&mut array[..])).unwrap() = 0;
+    assert_eq!(array, [2, 3, 0]);
+
+    let either: GenEither<Mutable, &i32, &mut i32> = GenRef::from(&mut array[0]).into();
+    *either.into_genref() = 5;
+    assert_eq!(array, [5, 3, 0]);
+}
+
+struct Config {
+    name: &'static str,
+    limits: Option<Box<Limits>>,
+    backup: Option<Box<Config>>,
+}
+
+struct Limits {
+    values: [u32; 2],
+}
+
+enum Entry {
+    Leaf(u32),
+    Branch { config: Config },
+}
+
+fn gen_backup_limit<M: Mutability>(config: GenRef<'_, M, Config>) -> Option<GenRef<'_, M, u32>> {
+    field!(&gen config.backup?.limits?.values[1])
+}
+
+#[test]
+fn field_optional_projections() {
+    let mut config = Config {
+        name: "main",
+        limits: None,
+        backup: Some(Box::new(Config {
+            name: "backup",
+            limits: Some(Box::new(Limits { values: [1, 2] })),
+            backup: None,
+        })),
+    };
+
+    *gen_backup_limit(GenRef::from(&mut config)).unwrap() = 20;
+    assert_eq!(gen_backup_limit(GenRef::from(&config)).unwrap(), &20);
+
+    let gen_config = GenRef::from(&config);
+    assert!(field!(&gen gen_config.limits?.values[0]).is_none());
+    let backup: GenRef<'_, Shared, Config> = field!(&gen gen_config.backup?.*).unwrap();
+    assert_eq!(*field!(&gen backup.name), "backup");
+
+    let mut entry = Entry::Branch { config };
+    let mut gen_entry = GenRef::from(&mut entry);
+    assert!(field!(&gen {GenRef::reborrow(&mut gen_entry)} as Entry::Leaf.0).is_none());
+    *field!(&gen gen_entry as Entry::Branch.config.backup?.name).unwrap() = "renamed";
+    match entry {
+        Entry::Branch { config } => assert_eq!(config.backup.unwrap().name, "renamed"),
+        Entry::Leaf(_) => unreachable!(),
+    }
+
+    let mut leaf = Entry::Leaf(1);
+    *field!(&gen {GenRef::from(&mut leaf)} as Entry::Leaf.0).unwrap() += 1;
+    assert!(matches!(leaf, Entry::Leaf(2)));
+}
+
+struct Parts {
+    name: &'static str,
+    pair: (u32, u32),
+    list: [u32; 3],
+}
+
+fn gen_swap_pair<M: Mutability>(
+    parts: GenRef<'_, M, Parts>,
+) -> (GenRef<'_, M, u32>, GenRef<'_, M, u32>) {
+    let (left, right) = GenRef::split_tuple(field!(&gen parts.pair));
+    (right, left)
+}
+
+#[test]
+fn disjoint_fields() {
+    let mut parts = Parts {
+        name: "parts",
+        pair: (1, 2),
+        list: [3, 4, 5],
+    };
+
+    let (name, mut first, mut last) =
+        fields!(&gen {GenRef::from(&mut parts)} => name, pair.0, list[2]);
+    *first += 10;
+    *last += *first;
+    assert_eq!(*name, "parts");
+    assert_eq!((parts.pair.0, parts.list), (11, [3, 4, 16]));
+
+    let (right, left) = gen_swap_pair(GenRef::from(&parts));
+    assert_eq!((*left, *right), (11, 2));
+
+    let (mut right, _) = gen_swap_pair(GenRef::from(&mut parts));
+    *right = 0;
+    assert_eq!(parts.pair, (11, 0));
+
+    let mut tuple = (1, "two", [3]);
+    let (_, _, mut three) = GenRef::split_tuple(GenRef::from(&mut tuple));
+    three[0] = 30;
+    assert_eq!(tuple.2, [30]);
+}
+
+enum Node {
+    Leaf(i32),
+    Pair(i32, i32),
+    List { items: Vec<i32>, weight: i32 },
+    Empty,
+}
+
+fn gen_primary<M: Mutability>(node: GenRef<'_, M, Node>) -> Option<GenRef<'_, M, i32>> {
+    gen_match!(node, {
+        Node::Leaf(value) | Node::Pair(value, _) => Some(value),
+        Node::List { items, weight: 0 } => gen_match!(GenRef::map_deref(items), {
+            [first, rest @ ..] if rest.is_empty() => Some(first),
+            [_, rest @ ..] => gen_match!(rest, {
+                [second, ..] => Some(second),
+                [] => None,
+            }),
+            [] => None,
+        }),
+        Node::List { weight, .. } => { Some(weight) }
+        Node::Empty => None
+    })
+}
+
+#[test]
+fn gen_match_bindings() {
+    let mut nodes = [
+        Node::Leaf(1),
+        Node::Pair(2, 3),
+        Node::List {
+            items: vec![4],
+            weight: 0,
+        },
+        Node::List {
+            items: vec![5, 6, 7],
+            weight: 0,
+        },
+        Node::List {
+            items: vec![],
+            weight: 8,
+        },
+        Node::Empty,
+    ];
+
+    let primaries: Vec<_> = nodes
+        .iter()
+        .map(|node| gen_primary(GenRef::from(node)).map(|value| *value))
+        .collect();
+    assert_eq!(
+        primaries,
+        [Some(1), Some(2), Some(4), Some(6), Some(8), None]
+    );
+
+    for node in &mut nodes {
+        if let Some(mut value) = gen_primary(GenRef::from(node)) {
+            *value *= 10;
+        }
+    }
+    assert!(matches!(nodes[1], Node::Pair(20, 3)));
+    assert!(matches!(&nodes[3], Node::List { items, .. } if items == &[5, 60, 7]));
+}
+
+#[test]
+fn reborrow_trait() {
+    fn gen_bump<M: Mutability>(genref: GenRef<'_, M, i32>) {
+        if let MutabilityEnum::Mutable(proof) = M::mutability() {
+            *GenRef::gen_into_mut(genref, proof) += 1;
+        }
+    }
+
+    let mut value = 1;
+    let mut genref = GenRef::from(&mut value);
+    gen_bump(GenRef::reborrow(&mut genref));
+    gen_bump(Reborrow::reborrow(&mut genref));
+    assert_eq!(*genref, 3);
+
+    let mut slot = [0, 0];
+    let mut reference = &mut slot[..];
+    reference.reborrow()[0] = 1;
+    reference.reborrow()[1] = 2;
+    assert_eq!(slot, [1, 2]);
+
+    let mut value = 5;
+    let mut optional = Some(GenRef::from(&mut value));
+    if let Some(mut genref) = optional.reborrow() {
+        *genref *= 2;
+    }
+    assert_eq!(*optional.unwrap(), 10);
+    let mut none: Option<&i32> = None;
+    assert!(none.reborrow().is_none());
+}
+
+#[test]
+fn method_argument_order() {
+    let mut values = [1, 2];
+    let mut genref = GenRef::from(&mut values[..]);
+    *GenRef::reborrow(&mut genref).map(|values| &mut values[1], |values| &values[1]) += 10;
+    *genref.try_map(<[_]>::first_mut, <[_]>::first).unwrap() += 20;
+    assert_eq!(values, [21, 12]);
+}
+
+#[test]
+fn downgrade_trait() {
+    fn gen_first<M: Mutability>(values: GenRef<'_, M, [i32]>) -> Option<GenRef<'_, Shared, i32>> {
+        GenRef::try_map(values, <[_]>::first, <[_]>::first_mut).downgrade()
+    }
+
+    let mut values = [1, 2, 3];
+    assert_eq!(*gen_first(GenRef::from(&mut values[..])).unwrap(), 1);
+    assert_eq!(*gen_first(GenRef::from(&values[..])).unwrap(), 1);
+
+    let [a, b, c] = values.each_mut().map(GenRef::from);
+    let (pair, (rest,)) = ([a, b], (Some(c),)).downgrade();
+    let shared: [GenRef<'_, Shared, i32>; 3] = [pair[0], pair[1], rest.unwrap()];
+    assert_eq!(shared.map(|value| *value), [1, 2, 3]);
+}
+
+#[test]
+fn gen_dyn_wrapper() {
+    let mut value = 1;
+    let mut wrapper: GenDyn<'_, Mutable, dyn core::fmt::Debug> =
+        GenDyn::from(&mut value as &mut dyn core::fmt::Debug);
+    assert_eq!(format!("{:?}", &*wrapper.reborrow()), "1");
+    let shared = wrapper.downgrade();
+    assert_eq!(format!("{:?}", &*shared), "1");
+    let genref = shared.into_genref();
+    assert_eq!(
+        format!(
+            "{:?}",
+            GenRef::into_shared(GenDyn::new(genref).into_genref())
+        ),
+        "1"
+    );
+}
+
+fn assert_send<T: Send>(_: T) {}
+
+fn assert_sync<T: Sync>(_: T) {}
+
+fn gen_assert_send<'a, M: Mutability, T>(genref: GenRef<'a, M, T>)
+where
+    GenRef<'a, M, T>: Send,
+{
+    assert_send(genref);
+}
+
+fn gen_assert_sync<M: Mutability, T: Sync>(genref: GenRef<'_, M, T>) {
+    assert_sync(genref);
+}
+
+#[test]
+fn send_sync_generic() {
+    let mut value = 1;
+    gen_assert_send(GenRef::from(&value));
+    gen_assert_sync(GenRef::from(&value));
+    gen_assert_send(GenRef::from(&mut value));
+    gen_assert_sync(GenRef::from(&mut value));
+
+    let mut cell = core::cell::Cell::new(1);
+    gen_assert_send(GenRef::from(&mut cell));
+}